@@ -0,0 +1,205 @@
+use crate::board::*;
+use crate::square::*;
+use crate::zobrist::*;
+
+// one bit per square, bit 0 is a1, bit 7 is h1, bit 63 is h8
+pub type Bitboard = u64;
+
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct Bitboards {
+    pub pieces: [[Bitboard; 6]; 2], // [colour index][piece type index]
+    pub colours: [Bitboard; 2],
+    pub occupied: Bitboard,
+}
+
+pub const EMPTY_BITBOARDS: Bitboards = Bitboards {
+    pieces: [[0; 6]; 2],
+    colours: [0; 2],
+    occupied: 0,
+};
+
+pub const fn square_bit(sq: Square) -> Bitboard {
+    return 1 << get_square_index(sq);
+}
+
+// white = 0, black = 1
+pub fn colour_index(colour: Colours) -> usize {
+    return match colour {
+        Colours::WHITE => 0,
+        Colours::BLACK => 1,
+        Colours::EMPTY => unreachable!(),
+    };
+}
+
+pub fn piece_type_index(piece_type: PieceTypes) -> usize {
+    debug_assert!(piece_type != PieceTypes::BLANK);
+    return piece_type as usize;
+}
+
+// removes and returns the lowest set square
+pub fn pop_lsb(bitboard: &mut Bitboard) -> Square {
+    let sq = get_lsb(*bitboard);
+    *bitboard &= *bitboard - 1;
+    return sq;
+}
+
+// the lowest set square, the bitboard must not be empty
+pub fn get_lsb(bitboard: Bitboard) -> Square {
+    debug_assert!(bitboard != 0);
+    return square_from_index_unchecked(bitboard.trailing_zeros() as usize);
+}
+
+// the only way pieces should be put on or taken off the board, so the board and bitboards agree
+pub fn set_piece(game_position: &mut Position, sq: Square, piece: Piece) {
+    let bit = square_bit(sq);
+    let old_piece = get_piece_on(game_position, sq);
+    let bitboards = &mut game_position.bitboards;
+    if old_piece.piece_type != PieceTypes::BLANK {
+        let c = colour_index(old_piece.colour);
+        bitboards.pieces[c][piece_type_index(old_piece.piece_type)] &= !bit;
+        bitboards.colours[c] &= !bit;
+        bitboards.occupied &= !bit;
+    }
+    if piece.piece_type != PieceTypes::BLANK {
+        let c = colour_index(piece.colour);
+        bitboards.pieces[c][piece_type_index(piece.piece_type)] |= bit;
+        bitboards.colours[c] |= bit;
+        bitboards.occupied |= bit;
+    }
+    let (x, y) = get_square_coordinates(sq);
+    game_position.board[x][y] = piece;
+    game_position.zobrist_key ^= get_piece_key(old_piece, sq) ^ get_piece_key(piece, sq);
+}
+
+/////////////////////////////////////////////////////////////////
+// precomputed attack tables
+
+const fn make_leaper_table(offsets: &[(i32, i32)]) -> [Bitboard; 64] {
+    let mut table = [0; 64];
+    let mut sq = 0;
+    while sq < 64 {
+        let x = (sq % 8) as i32;
+        let y = (sq / 8) as i32;
+        let mut i = 0;
+        while i < offsets.len() {
+            let xx = x + offsets[i].0;
+            let yy = y + offsets[i].1;
+            if xx >= 0 && xx < 8 && yy >= 0 && yy < 8 {
+                table[sq] |= 1 << (yy * 8 + xx);
+            }
+            i += 1;
+        }
+        sq += 1;
+    }
+    return table;
+}
+
+const KNIGHT_ATTACKS: [Bitboard; 64] = make_leaper_table(&[(1, 2), (2, 1), (2, -1), (1, -2), (-1, -2), (-2, -1), (-2, 1), (-1, 2)]);
+const KING_ATTACKS: [Bitboard; 64] = make_leaper_table(&[(1, 0), (1, 1), (0, 1), (-1, 1), (-1, 0), (-1, -1), (0, -1), (1, -1)]);
+const PAWN_ATTACKS: [[Bitboard; 64]; 2] = [
+    make_leaper_table(&[(-1, 1), (1, 1)]),
+    make_leaper_table(&[(-1, -1), (1, -1)]),
+];
+
+pub fn get_knight_attacks(sq: Square) -> Bitboard {
+    return KNIGHT_ATTACKS[get_square_index(sq)];
+}
+
+pub fn get_king_attacks(sq: Square) -> Bitboard {
+    return KING_ATTACKS[get_square_index(sq)];
+}
+
+// the squares a pawn of the given colour index attacks
+pub fn get_pawn_attacks(c: usize, sq: Square) -> Bitboard {
+    return PAWN_ATTACKS[c][get_square_index(sq)];
+}
+
+// the first four directions increase the square index, the last four decrease it
+const DIRECTIONS: [(i32, i32); 8] = [(0, 1), (1, 1), (1, 0), (-1, 1), (0, -1), (-1, -1), (-1, 0), (1, -1)];
+const NORTH: usize = 0;
+const NORTH_EAST: usize = 1;
+const EAST: usize = 2;
+const NORTH_WEST: usize = 3;
+const SOUTH: usize = 4;
+const SOUTH_WEST: usize = 5;
+const WEST: usize = 6;
+const SOUTH_EAST: usize = 7;
+
+const fn make_rays() -> [[Bitboard; 64]; 8] {
+    let mut rays = [[0; 64]; 8];
+    let mut direction = 0;
+    while direction < 8 {
+        let mut sq = 0;
+        while sq < 64 {
+            let mut xx = (sq % 8) as i32 + DIRECTIONS[direction].0;
+            let mut yy = (sq / 8) as i32 + DIRECTIONS[direction].1;
+            while xx >= 0 && xx < 8 && yy >= 0 && yy < 8 {
+                rays[direction][sq] |= 1 << (yy * 8 + xx);
+                xx += DIRECTIONS[direction].0;
+                yy += DIRECTIONS[direction].1;
+            }
+            sq += 1;
+        }
+        direction += 1;
+    }
+    return rays;
+}
+
+// every square from a square to the edge of the board, excluding the square itself
+const RAYS: [[Bitboard; 64]; 8] = make_rays();
+
+// squares along a ray up to and including the first piece in the way
+fn get_ray_attacks(direction: usize, sq: Square, occupied: Bitboard) -> Bitboard {
+    let ray = RAYS[direction][get_square_index(sq)];
+    let blockers = ray & occupied;
+    if blockers == 0 {
+        return ray;
+    }
+    let first_blocker = if direction < 4 {
+        blockers.trailing_zeros() as usize
+    } else {
+        63 - blockers.leading_zeros() as usize
+    };
+    return ray ^ RAYS[direction][first_blocker];
+}
+
+pub fn get_rook_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    return get_ray_attacks(NORTH, sq, occupied) |
+        get_ray_attacks(EAST, sq, occupied) |
+        get_ray_attacks(SOUTH, sq, occupied) |
+        get_ray_attacks(WEST, sq, occupied);
+}
+
+pub fn get_bishop_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    return get_ray_attacks(NORTH_EAST, sq, occupied) |
+        get_ray_attacks(NORTH_WEST, sq, occupied) |
+        get_ray_attacks(SOUTH_EAST, sq, occupied) |
+        get_ray_attacks(SOUTH_WEST, sq, occupied);
+}
+
+pub fn get_queen_attacks(sq: Square, occupied: Bitboard) -> Bitboard {
+    return get_rook_attacks(sq, occupied) | get_bishop_attacks(sq, occupied);
+}
+
+// the whole rank, file or diagonal two squares are both on, otherwise none
+pub fn get_line_through(from: Square, to: Square) -> Bitboard {
+    let from_index = get_square_index(from);
+    for direction in 0..8 {
+        if RAYS[direction][from_index] & square_bit(to) != 0 {
+            return RAYS[direction][from_index] | RAYS[(direction + 4) % 8][from_index] | square_bit(from);
+        }
+    }
+    return 0;
+}
+
+// the squares strictly between two squares on the same rank, file or diagonal, otherwise none
+pub fn get_squares_between(from: Square, to: Square) -> Bitboard {
+    let (from_index, to_index) = (get_square_index(from), get_square_index(to));
+    for direction in 0..8 {
+        if RAYS[direction][from_index] & square_bit(to) != 0 {
+            // the opposite direction is four along
+            return RAYS[direction][from_index] & RAYS[(direction + 4) % 8][to_index];
+        }
+    }
+    return 0;
+}
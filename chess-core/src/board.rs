@@ -0,0 +1,320 @@
+use crate::rules::*;
+use crate::bitboard::*;
+use crate::square::*;
+use crate::zobrist::*;
+use rand::Rng;
+
+#[derive(Copy, Clone, Hash, Eq, PartialEq)]
+pub enum PieceNames {
+    WhiteKing,
+    WhiteQueen,
+    WhiteBishop,
+    WhiteKnight,
+    WhiteRook,
+    WhitePawn,
+    BlackKing,
+    BlackQueen,
+    BlackBishop,
+    BlackKnight,
+    BlackRook,
+    BlackPawn,
+    Blank,
+}
+
+#[derive(Copy, Clone, Hash, Eq, PartialEq)]
+pub enum PieceTypes {
+    ROOK,
+    KNIGHT,
+    BISHOP,
+    QUEEN,
+    KING,
+    PAWN,
+    BLANK,
+}
+
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "lowercase"))]
+pub enum Colours {
+    WHITE = 1,
+    EMPTY = 0,
+    BLACK = -1
+}
+
+// serialized as its FEN letter, "." for a blank square
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(into = "String", try_from = "String"))]
+pub struct Piece {
+    pub piece_name: PieceNames,
+    pub piece_type: PieceTypes,
+    pub colour:Colours,
+}
+
+// serialized with square names, e.g. {"from": "e2", "to": "e4", "piece": "P", ...}
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+    serde(into = "crate::serialization::SerializedMove", try_from = "crate::serialization::SerializedMove"))]
+pub struct Move {
+    pub start: Square,
+    pub end: Square,
+    pub start_piece: Piece, 
+    pub end_piece: Piece,
+    pub promotion: Piece, // BLANK_PIECE unless a pawn promotes
+}
+
+// a move in 16 bits, for move lists: the start square in bits 0-5, the end square in bits 6-11
+// and the promotion piece type + 1 in bits 12-14. the pieces themselves are looked up in the
+// position by unpack_move, castling is the king taking its own rook as in Move
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
+pub struct CompactMove(u16);
+
+pub const NULL_COMPACT_MOVE: CompactMove = CompactMove(0);
+
+// no legal position has more than 218 moves
+pub const MAX_MOVES: usize = 256;
+
+// a move list on the stack, so generating moves doesn't allocate
+#[derive(Copy, Clone)]
+pub struct MoveList {
+    pub moves: [CompactMove; MAX_MOVES],
+    pub len: usize,
+}
+
+// everything play_move overwrites that can't be worked out from the move itself
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct UndoRecord {
+    pub m: Move,
+    pub captured_piece: Piece, // the pawn taken en passant is not on the end square
+    pub en_passant: Option<File>,
+    pub castling_rights: u8,
+    pub halfmove_clock: u32,
+    pub fullmove_number: u32,
+    pub zobrist_key: u64,
+}
+
+// serialized as its FEN together with the start position and moves, so undo still works
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize),
+    serde(into = "crate::serialization::SerializedPosition", try_from = "crate::serialization::SerializedPosition"))]
+pub struct Position {
+    pub board:  [[Piece; 8]; 8],
+    pub bitboards: Bitboards, // kept in sync with board by set_piece
+    pub turn: i8,
+    pub en_passant: Option<File>, // the file of a pawn that has just moved two squares
+    pub castling_rights: u8, // WHITE_KINGSIDE | BLACK_KINGSIDE | WHITE_QUEENSIDE | BLACK_QUEENSIDE
+    pub castling_rook_files: [File; 4], // the file of the rook for each castling right, in the same order
+    pub chess960: bool,
+    pub halfmove_clock: u32, // plies since the last capture or pawn move
+    pub fullmove_number: u32,
+    pub undo_history: Vec<UndoRecord>, // one per move played, for undo_move
+    pub zobrist_key: u64, // Polyglot compatible, kept up to date by set_piece, play_move and undo_move
+}
+
+
+pub const WHITE_KING_PIECE: Piece = Piece { piece_name: PieceNames::WhiteKing, piece_type: PieceTypes::KING, colour: Colours::WHITE };
+pub const WHITE_QUEEN_PIECE: Piece = Piece { piece_name: PieceNames::WhiteQueen, piece_type: PieceTypes::QUEEN, colour: Colours::WHITE };
+pub const WHITE_ROOK_PIECE: Piece = Piece { piece_name: PieceNames::WhiteRook, piece_type: PieceTypes::ROOK, colour: Colours::WHITE };
+pub const WHITE_KNIGHT_PIECE: Piece = Piece { piece_name: PieceNames::WhiteKnight, piece_type: PieceTypes::KNIGHT, colour: Colours::WHITE };
+pub const WHITE_BISHOP_PIECE: Piece = Piece { piece_name: PieceNames::WhiteBishop, piece_type: PieceTypes::BISHOP, colour: Colours::WHITE };
+pub const WHITE_PAWN_PIECE: Piece = Piece { piece_name: PieceNames::WhitePawn, piece_type: PieceTypes::PAWN, colour: Colours::WHITE };
+
+pub const BLACK_KING_PIECE: Piece = Piece { piece_name: PieceNames::BlackKing, piece_type: PieceTypes::KING, colour: Colours::BLACK};
+pub const BLACK_QUEEN_PIECE: Piece = Piece { piece_name: PieceNames::BlackQueen, piece_type: PieceTypes::QUEEN, colour: Colours::BLACK};
+pub const BLACK_ROOK_PIECE: Piece = Piece { piece_name: PieceNames::BlackRook, piece_type: PieceTypes::ROOK, colour: Colours::BLACK};
+pub const BLACK_KNIGHT_PIECE: Piece = Piece { piece_name: PieceNames::BlackKnight, piece_type: PieceTypes::KNIGHT, colour: Colours::BLACK};
+pub const BLACK_BISHOP_PIECE: Piece = Piece { piece_name: PieceNames::BlackBishop, piece_type: PieceTypes::BISHOP, colour: Colours::BLACK};
+pub const BLACK_PAWN_PIECE: Piece = Piece { piece_name: PieceNames::BlackPawn, piece_type: PieceTypes::PAWN, colour: Colours::BLACK};
+
+pub const BLANK_PIECE: Piece = Piece { piece_name: PieceNames::Blank, piece_type: PieceTypes::BLANK, colour: Colours::EMPTY };
+
+pub fn empty_position() -> Position {
+    let mut game_position = Position {
+        board: [[BLANK_PIECE; 8]; 8],
+        bitboards: EMPTY_BITBOARDS,
+        turn: 1,
+        en_passant: None,
+        castling_rights: 0,
+        castling_rook_files: [FILE_H, FILE_H, FILE_A, FILE_A],
+        chess960: false,
+        halfmove_clock: 0,
+        fullmove_number: 1,
+        undo_history: vec![],
+        zobrist_key: 0,
+    };
+    game_position.zobrist_key = compute_zobrist_key(&game_position);
+    return game_position;
+}
+
+pub fn initialise_board(game_position: &mut Position)  {
+    initialise_chess960_board(game_position, CLASSICAL_CHESS960_INDEX);
+    game_position.chess960 = false;
+}
+
+// the classical start position in the standard numbering of the 960 start positions
+pub const CLASSICAL_CHESS960_INDEX: usize = 518;
+
+// the back rank of the Chess960 start position with the given index (0 to 959), numbered as in
+// https://en.wikipedia.org/wiki/Fischer_random_chess_numbering_scheme
+pub fn get_chess960_back_rank(index: usize) -> [PieceTypes; 8] {
+    let mut back_rank = [PieceTypes::BLANK; 8];
+    let mut n = index % 960;
+
+    // bishops on opposite colours
+    back_rank[n % 4 * 2 + 1] = PieceTypes::BISHOP;
+    n /= 4;
+    back_rank[n % 4 * 2] = PieceTypes::BISHOP;
+    n /= 4;
+
+    // the queen and knights go on the nth remaining empty square
+    let mut place_on_empty = |piece_type: PieceTypes, i: usize| {
+        let x = (0..8).filter(|x| back_rank[*x] == PieceTypes::BLANK).nth(i).unwrap();
+        back_rank[x] = piece_type;
+    };
+    place_on_empty(PieceTypes::QUEEN, n % 6);
+    n /= 6;
+    let knights = [(0, 1), (0, 2), (0, 3), (0, 4), (1, 2), (1, 3), (1, 4), (2, 3), (2, 4), (3, 4)][n];
+    place_on_empty(PieceTypes::KNIGHT, knights.1);
+    place_on_empty(PieceTypes::KNIGHT, knights.0);
+
+    // the king always ends up between the rooks
+    place_on_empty(PieceTypes::ROOK, 0);
+    place_on_empty(PieceTypes::KING, 0);
+    place_on_empty(PieceTypes::ROOK, 0);
+    return back_rank;
+}
+
+pub fn get_random_chess960_index() -> usize {
+    return rand::rng().random_range(0..960);
+}
+
+pub fn initialise_chess960_board(game_position: &mut Position, index: usize) {
+    let back_rank = get_chess960_back_rank(index);
+    for file in all_files() {
+        let piece_type = back_rank[get_file_index(file)];
+        set_piece(game_position, make_square(file, RANK_2), WHITE_PAWN_PIECE);
+        set_piece(game_position, make_square(file, RANK_7), BLACK_PAWN_PIECE);
+        set_piece(game_position, make_square(file, RANK_1), get_piece(piece_type, Colours::WHITE));
+        set_piece(game_position, make_square(file, RANK_8), get_piece(piece_type, Colours::BLACK));
+    }
+
+    let rook_files: Vec<File> = all_files().filter(|file| back_rank[get_file_index(*file)] == PieceTypes::ROOK).collect();
+    game_position.castling_rook_files = [rook_files[1], rook_files[1], rook_files[0], rook_files[0]];
+    game_position.castling_rights = ALL_CASTLING_RIGHTS;
+    game_position.chess960 = true;
+    game_position.zobrist_key = compute_zobrist_key(game_position);
+}
+
+pub fn get_piece_on(game_position: &Position, sq: Square) -> Piece {
+    let (x, y) = get_square_coordinates(sq);
+    return game_position.board[x][y];
+}
+
+pub fn get_piece(piece_type: PieceTypes, colour: Colours) -> Piece {
+    let pieces = if colour == Colours::WHITE {
+        [WHITE_ROOK_PIECE, WHITE_KNIGHT_PIECE, WHITE_BISHOP_PIECE, WHITE_QUEEN_PIECE, WHITE_KING_PIECE, WHITE_PAWN_PIECE]
+    } else {
+        [BLACK_ROOK_PIECE, BLACK_KNIGHT_PIECE, BLACK_BISHOP_PIECE, BLACK_QUEEN_PIECE, BLACK_KING_PIECE, BLACK_PAWN_PIECE]
+    };
+    if piece_type == PieceTypes::BLANK {
+        return BLANK_PIECE;
+    }
+    return pieces[piece_type as usize];
+}
+
+pub fn get_compact_move(start: Square, end: Square, promotion_type: PieceTypes) -> CompactMove {
+    let promotion_bits = if promotion_type == PieceTypes::BLANK { 0 } else { promotion_type as u16 + 1 };
+    return CompactMove(get_square_index(start) as u16 | (get_square_index(end) as u16) << 6 | promotion_bits << 12);
+}
+
+pub fn get_compact_move_start(cm: CompactMove) -> Square {
+    return square_from_index_unchecked((cm.0 & 63) as usize);
+}
+
+pub fn get_compact_move_end(cm: CompactMove) -> Square {
+    return square_from_index_unchecked((cm.0 >> 6 & 63) as usize);
+}
+
+pub fn get_compact_move_promotion(cm: CompactMove) -> PieceTypes {
+    return match cm.0 >> 12 {
+        0 => PieceTypes::BLANK,
+        1 => PieceTypes::ROOK,
+        2 => PieceTypes::KNIGHT,
+        3 => PieceTypes::BISHOP,
+        4 => PieceTypes::QUEEN,
+        _ => unreachable!(),
+    };
+}
+
+pub fn pack_move(m: Move) -> CompactMove {
+    return get_compact_move(m.start, m.end, m.promotion.piece_type);
+}
+
+// the full move, with the pieces as they stand in the position the move is for
+pub fn unpack_move(game_position: &Position, cm: CompactMove) -> Move {
+    let (start, end) = (get_compact_move_start(cm), get_compact_move_end(cm));
+    let start_piece = get_piece_on(game_position, start);
+    return Move {
+        start,
+        end,
+        start_piece,
+        end_piece: get_piece_on(game_position, end),
+        promotion: get_piece(get_compact_move_promotion(cm), start_piece.colour),
+    };
+}
+
+pub fn new_move_list() -> MoveList {
+    return MoveList { moves: [NULL_COMPACT_MOVE; MAX_MOVES], len: 0 };
+}
+
+pub fn push_move(move_list: &mut MoveList, cm: CompactMove) {
+    move_list.moves[move_list.len] = cm;
+    move_list.len += 1;
+}
+
+// the moves that have been pushed
+pub fn get_moves(move_list: &MoveList) -> &[CompactMove] {
+    return &move_list.moves[..move_list.len];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn get_back_rank_letters(index: usize) -> String {
+        return get_chess960_back_rank(index).iter().map(|piece_type| match piece_type {
+            PieceTypes::KING => 'K',
+            PieceTypes::QUEEN => 'Q',
+            PieceTypes::ROOK => 'R',
+            PieceTypes::BISHOP => 'B',
+            PieceTypes::KNIGHT => 'N',
+            PieceTypes::PAWN => 'P',
+            PieceTypes::BLANK => '.',
+        }).collect();
+    }
+
+    #[test]
+    fn chess960_numbering() {
+        assert_eq!(get_back_rank_letters(0), "BBQNNRKR");
+        assert_eq!(get_back_rank_letters(CLASSICAL_CHESS960_INDEX), "RNBQKBNR");
+        assert_eq!(get_back_rank_letters(959), "RKRNNQBB");
+    }
+
+    // every index gives a different back rank with the bishops on opposite colours and the king
+    // between the rooks
+    #[test]
+    fn chess960_back_ranks_are_distinct_and_valid() {
+        let mut seen = HashSet::new();
+        for index in 0..960 {
+            let letters = get_back_rank_letters(index);
+            let mut sorted: Vec<char> = letters.chars().collect();
+            sorted.sort();
+            assert_eq!(sorted.iter().collect::<String>(), "BBKNNQRR", "{}: {}", index, letters);
+            let bishops: Vec<usize> = letters.match_indices('B').map(|(x, _)| x).collect();
+            assert_ne!(bishops[0] % 2, bishops[1] % 2, "{}: {}", index, letters);
+            let rooks: Vec<usize> = letters.match_indices('R').map(|(x, _)| x).collect();
+            let king = letters.find('K').unwrap();
+            assert!(rooks[0] < king && king < rooks[1], "{}: {}", index, letters);
+            assert!(seen.insert(letters), "{} repeats a back rank", index);
+        }
+    }
+}
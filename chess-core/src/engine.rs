@@ -0,0 +1,311 @@
+use crate::rules::*;
+use crate::board::*;
+use crate::bitboard::*;
+use crate::square::*;
+use rand::Rng;
+use std::collections::HashMap;
+use std::cmp::{min, max};
+
+const MATE_SCORE: i64 = 10000;
+
+// in pawns. the king is never exchanged, its value only has to outweigh everything else
+pub fn get_piece_value(piece_type: PieceTypes) -> i64 {
+    return match piece_type {
+        PieceTypes::PAWN => 1,
+        PieceTypes::KNIGHT => 3,
+        PieceTypes::BISHOP => 3,
+        PieceTypes::ROOK => 5,
+        PieceTypes::QUEEN => 9,
+        PieceTypes::KING => 1000,
+        PieceTypes::BLANK => 0,
+    };
+}
+
+fn evaluate_position(game_position: &mut Position) -> i64{
+    let mut score: i64 = 0;
+    for sq in all_squares() {
+        let piece = get_piece_on(game_position, sq);
+        if piece.piece_type != PieceTypes::KING {
+            score += piece.colour as i64 * get_piece_value(piece.piece_type);
+        }
+    }
+    return score
+}
+
+// the least valuable of the attackers, with its value
+fn get_least_valuable_attacker(game_position: &Position, attackers: Bitboard) -> Option<(Square, i64)> {
+    for piece_type in [PieceTypes::PAWN, PieceTypes::KNIGHT, PieceTypes::BISHOP, PieceTypes::ROOK, PieceTypes::QUEEN, PieceTypes::KING] {
+        let i = piece_type_index(piece_type);
+        let of_type = attackers & (game_position.bitboards.pieces[0][i] | game_position.bitboards.pieces[1][i]);
+        if of_type != 0 {
+            return Some((get_lsb(of_type), get_piece_value(piece_type)));
+        }
+    }
+    return None;
+}
+
+// the material the side making m wins, in pawns, if both sides keep recapturing on the end
+// square with their least valuable piece for as long as it pays. taking a piece off the board
+// uncovers any slider behind it, pins are ignored
+pub fn static_exchange_evaluation(game_position: &Position, m: Move) -> i64 {
+    if is_castling_move(m) {
+        return 0;
+    }
+    let mut occupied = game_position.bitboards.occupied & !square_bit(m.start);
+    let mut gains = [0; 32];
+    gains[0] = get_piece_value(m.end_piece.piece_type);
+    if is_move_en_passant(m) {
+        gains[0] = get_piece_value(PieceTypes::PAWN);
+        occupied &= !square_bit(get_en_passant_captured_square(m));
+    }
+    let mut on_square_value = get_piece_value(m.start_piece.piece_type);
+    if m.promotion != BLANK_PIECE {
+        gains[0] += get_piece_value(m.promotion.piece_type) - get_piece_value(PieceTypes::PAWN);
+        on_square_value = get_piece_value(m.promotion.piece_type);
+    }
+
+    // gains[depth] is what the side making the depth-th capture wins if the exchange stops there
+    let mut side = get_opposite_colour(m.start_piece.colour);
+    let mut depth = 0;
+    loop {
+        let attackers = get_square_attackers_through(game_position, m.end, side, occupied) & occupied;
+        let (attacker_sq, attacker_value) = match get_least_valuable_attacker(game_position, attackers) {
+            Some(attacker) => attacker,
+            None => break,
+        };
+        // the king can only take last
+        let other_side = get_opposite_colour(side);
+        if attacker_value == get_piece_value(PieceTypes::KING) && get_square_attackers_through(game_position, m.end, other_side, occupied) & occupied != 0 {
+            break;
+        }
+        depth += 1;
+        gains[depth] = on_square_value - gains[depth - 1];
+        on_square_value = attacker_value;
+        occupied &= !square_bit(attacker_sq);
+        side = other_side;
+    }
+
+    // each side may stop recapturing when carrying on would lose more
+    while depth > 0 {
+        gains[depth - 1] = -max(-gains[depth - 1], gains[depth]);
+        depth -= 1;
+    }
+    return gains[0];
+}
+
+// the pieces of the colour that the other side wins material by taking. the king is left out,
+// an attack on it is a check rather than a piece to win
+pub fn get_hanging_pieces(game_position: &Position, colour: Colours) -> Bitboard {
+    let mut hanging = 0;
+    let c = colour_index(colour);
+    let mut pieces = game_position.bitboards.colours[c] & !game_position.bitboards.pieces[c][piece_type_index(PieceTypes::KING)];
+    while pieces != 0 {
+        let sq = pop_lsb(&mut pieces);
+        let mut attackers = get_square_attackers(game_position, sq, get_opposite_colour(colour));
+        while attackers != 0 {
+            let m = create_move(game_position, pop_lsb(&mut attackers), sq);
+            if static_exchange_evaluation(game_position, m) > 0 {
+                hanging |= square_bit(sq);
+                break;
+            }
+        }
+    }
+    return hanging;
+}
+
+// captures that don't lose material, best first, and the ones that do, which are left until
+// after the quiet moves
+fn order_captures(position: &Position, captures: &MoveList) -> (MoveList, MoveList) {
+    // each capture's exchange is only evaluated once, the sort and the split both use it. they
+    // are kept on the stack, like the move lists, as this runs at every node of the search
+    let mut scores = [(NULL_COMPACT_MOVE, 0); MAX_MOVES];
+    let scored_captures = &mut scores[..captures.len];
+    for (scored, cm) in scored_captures.iter_mut().zip(get_moves(captures)) {
+        *scored = (*cm, static_exchange_evaluation(position, unpack_move(position, *cm)));
+    }
+    scored_captures.sort_unstable_by_key(|(_, see)| -see);
+    let mut good_captures = new_move_list();
+    let mut bad_captures = new_move_list();
+    for (cm, see) in scored_captures.iter().copied() {
+        if see >= 0 {
+            push_move(&mut good_captures, cm);
+        } else {
+            push_move(&mut bad_captures, cm);
+        }
+    }
+    return (good_captures, bad_captures);
+}
+
+// searches the moves in move_list, keeping the best eval for the side to move. returns true once
+// beta <= alpha, when the rest of the position's moves can be skipped
+fn search_moves(position: &mut Position, move_list: &MoveList, depth: usize, alpha: &mut i64, beta: &mut i64, is_white: bool, best_eval: &mut i64) -> bool {
+    for cm in get_moves(move_list) {
+        play_move(position, unpack_move(position, *cm));
+        let eval = minimax(position, depth-1, *alpha, *beta, !is_white);
+        undo_move(position);
+        if is_white {
+            *best_eval = max(*best_eval, eval);
+            *alpha = max(*alpha, eval);
+        } else {
+            *best_eval = min(*best_eval, eval);
+            *beta = min(*beta, eval);
+        }
+        if *beta <= *alpha {
+            return true;
+        }
+    }
+    return false;
+}
+
+fn minimax(position: &mut Position, depth: usize, mut alpha: i64, mut beta: i64, is_white: bool) -> i64 {
+    if is_threefold_repetition(position) || is_insufficient_material(position) {
+        return 0;
+    }
+    if depth == 0 {
+        return evaluate_position(position);
+    }
+
+    // captures that don't lose material are searched first as they are the likeliest to cut the
+    // search short, and quiet moves are only generated if they don't, or to tell whether the
+    // game is over
+    let mut captures = new_move_list();
+    generate_legal_moves(position, MoveStage::CAPTURES, &mut captures);
+    let mut quiets = new_move_list();
+    let quiets_generated = captures.len == 0;
+    if quiets_generated {
+        generate_legal_moves(position, MoveStage::QUIETS, &mut quiets);
+    }
+    match get_game_outcome_with_legal_moves(position, captures.len + quiets.len > 0) {
+        GameOutcome::Ongoing => {}
+        GameOutcome::Checkmate { winner } => {
+            // prefer quicker mates, which are found with more depth remaining
+            return winner as i64 * (MATE_SCORE + depth as i64);
+        }
+        _ => {
+            return 0;
+        }
+    }
+
+    let mut best_eval: i64 = if is_white { -99999 } else { 99999 };
+    let (good_captures, bad_captures) = order_captures(position, &captures);
+    if search_moves(position, &good_captures, depth, &mut alpha, &mut beta, is_white, &mut best_eval) {
+        return best_eval;
+    }
+    if !quiets_generated {
+        generate_legal_moves(position, MoveStage::QUIETS, &mut quiets);
+    }
+    if search_moves(position, &quiets, depth, &mut alpha, &mut beta, is_white, &mut best_eval) {
+        return best_eval;
+    }
+    // one ply from the horizon a losing capture is scored by the piece it takes and not by the
+    // recapture, so it would look better than it is. it is pruned there unless nothing else can
+    // be played
+    if depth == 1 && good_captures.len + quiets.len > 0 {
+        return best_eval;
+    }
+    search_moves(position, &bad_captures, depth, &mut alpha, &mut beta, is_white, &mut best_eval);
+    return best_eval;
+}
+
+pub fn get_engine_move(game_position: &mut Position) -> Move {
+    let mut rng = rand::rng();
+    let legal_moves = get_all_legal_moves(game_position);
+    let mut evals: HashMap<Move, i64> = Default::default();
+    let mut best_moves: Vec<Move> = vec![];
+
+    for m in legal_moves {
+        play_move(game_position, m);
+        let eval = minimax(game_position, 3, -99999, 99999, game_position.turn==1);
+        undo_move(game_position);
+        evals.insert(m, eval);
+    }
+
+    if game_position.turn == 1 {
+        let max_eval = *evals.values().max().unwrap();
+        for (k, v) in evals.iter() {
+            if *v == max_eval {
+                best_moves.push(*k);
+            }
+        }
+    } else {
+        let min_eval = *evals.values().min().unwrap();
+        for (k, v) in evals.iter() {
+            if *v == min_eval {
+                best_moves.push(*k);
+            }
+        }
+    }
+
+    let r = rng.random_range(0..best_moves.len());
+    return best_moves[r];
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::*;
+
+    fn get_see(fen: &str, uci: &str) -> i64 {
+        let mut game_position = position_from_fen(fen).unwrap();
+        let m = move_from_uci(&mut game_position, uci).unwrap();
+        return static_exchange_evaluation(&game_position, m);
+    }
+
+    fn get_hanging_square_names(fen: &str, colour: Colours) -> Vec<String> {
+        let game_position = position_from_fen(fen).unwrap();
+        let mut hanging = get_hanging_pieces(&game_position, colour);
+        let mut names = vec![];
+        while hanging != 0 {
+            names.push(get_square_name(pop_lsb(&mut hanging)));
+        }
+        return names;
+    }
+
+    #[test]
+    fn see_of_winning_and_losing_captures() {
+        assert_eq!(get_see("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", "e4d5"), 1);
+        assert_eq!(get_see("4k3/2p5/3p4/8/8/8/3R4/4K3 w - - 0 1", "d2d6"), -4);
+        assert_eq!(get_see("1k1r4/1pp4p/p7/4p3/8/P5P1/1PP4P/2K1R3 w - - 0 1", "e1e5"), 1);
+        assert_eq!(get_see("1k1r3q/1ppn3p/p4b2/4p3/8/P2N2P1/1PP1R1BP/2K1Q3 w - - 0 1", "d3e5"), -2);
+    }
+
+    // the second rook recaptures through the first
+    #[test]
+    fn see_counts_pieces_behind_the_first_attacker() {
+        assert_eq!(get_see("4k3/3r4/3p4/8/8/8/3R4/3RK3 w - - 0 1", "d2d6"), 1);
+    }
+
+    #[test]
+    fn see_of_en_passant() {
+        assert_eq!(get_see("4k3/8/8/3pP3/8/8/8/4K3 w - d6 0 3", "e5d6"), 1);
+    }
+
+    #[test]
+    fn see_of_promotions() {
+        assert_eq!(get_see("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7b8q"), 11);
+        assert_eq!(get_see("1n2k3/P7/8/8/8/8/8/4K3 w - - 0 1", "a7a8q"), 8);
+    }
+
+    // the rook wins a rook, the pawn trade is even and the queen loses herself for a knight
+    #[test]
+    fn captures_are_ordered_by_exchange() {
+        let mut game_position = position_from_fen("4k3/7r/2p5/1n1p4/4P3/8/8/1Q2K2R w K - 0 1").unwrap();
+        let mut captures = new_move_list();
+        generate_legal_moves(&mut game_position, MoveStage::CAPTURES, &mut captures);
+        let (good_captures, bad_captures) = order_captures(&game_position, &captures);
+        let get_uci = |move_list: &MoveList| -> Vec<String> {
+            return get_moves(move_list).iter().map(|cm| move_to_uci(&game_position, unpack_move(&game_position, *cm))).collect();
+        };
+        assert_eq!(get_uci(&good_captures), ["h1h7", "e4d5"]);
+        assert_eq!(get_uci(&bad_captures), ["b1b5"]);
+    }
+
+    #[test]
+    fn hanging_pieces() {
+        assert_eq!(get_hanging_square_names("4k3/8/8/3p4/4P3/8/8/4K3 w - - 0 1", Colours::BLACK), ["d5"]);
+        assert!(get_hanging_square_names("4k3/2p5/3p4/8/8/8/3R4/4K3 w - - 0 1", Colours::BLACK).is_empty());
+        assert!(get_hanging_square_names("4k3/2p5/3p4/8/8/8/3R4/4K3 w - - 0 1", Colours::WHITE).is_empty());
+        // a king in check isn't hanging
+        assert!(get_hanging_square_names("4k3/8/8/8/8/8/8/R3K2r w Q - 0 1", Colours::WHITE).is_empty());
+    }
+}
@@ -0,0 +1,408 @@
+use std::time::Duration;
+use crate::board::*;
+use crate::rules::*;
+use crate::notation::*;
+use crate::record::*;
+
+// numeric annotation glyphs about the move itself, numbered as in PGN ($1 to $6)
+pub const NAG_GOOD_MOVE: u8 = 1; // !
+pub const NAG_MISTAKE: u8 = 2; // ?
+pub const NAG_BRILLIANT_MOVE: u8 = 3; // !!
+pub const NAG_BLUNDER: u8 = 4; // ??
+pub const NAG_INTERESTING_MOVE: u8 = 5; // !?
+pub const NAG_DUBIOUS_MOVE: u8 = 6; // ?!
+
+// from white's point of view, like the engine's evaluation
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum Evaluation {
+    Centipawns(i64),
+    MateIn(i64), // in moves, negative when black is the one mating
+}
+
+// an index into GameTree::nodes. nodes are never removed, so ids stay valid
+pub type NodeId = usize;
+
+pub const ROOT_NODE: NodeId = 0;
+
+#[derive(Clone, Eq, PartialEq)]
+pub struct GameNode {
+    pub m: Option<Move>, // the move leading here, None only for the root
+    pub parent: Option<NodeId>,
+    pub children: Vec<NodeId>, // the first continues the line, the rest are variations in order
+    pub nags: Vec<u8>,
+    pub comment: String, // about the position after the move, or for the root the whole game
+    pub clock: Option<Duration>, // time left for the side that made the move
+    pub eval: Option<Evaluation>,
+}
+
+// a game with its variations and annotations. position is always the position at current, the
+// navigation functions play and undo moves on it so its undo history follows the tree back to
+// the root
+#[derive(Clone, Eq, PartialEq)]
+pub struct GameTree {
+    pub nodes: Vec<GameNode>,
+    pub current: NodeId,
+    pub position: Position,
+}
+
+fn new_node(m: Option<Move>, parent: Option<NodeId>) -> GameNode {
+    return GameNode {
+        m,
+        parent,
+        children: vec![],
+        nags: vec![],
+        comment: String::new(),
+        clock: None,
+        eval: None,
+    };
+}
+
+// the root is start_position, which can't be gone back past
+pub fn new_game_tree(start_position: Position) -> GameTree {
+    return GameTree {
+        nodes: vec![new_node(None, None)],
+        current: ROOT_NODE,
+        position: start_position,
+    };
+}
+
+// the record's moves as the main line, with the current node at the end. the tree doesn't keep
+// the outcome
+pub fn game_tree_from_record(record: &GameRecord) -> Result<GameTree, GameRecordError> {
+    let start_record = GameRecord { moves: vec![], ..record.clone() };
+    let mut tree = new_game_tree(position_from_game_record(&start_record)?);
+    for (index, m) in record.moves.iter().enumerate() {
+        if !get_all_legal_moves(&mut tree.position).contains(m) {
+            return Err(GameRecordError::IllegalMove(index));
+        }
+        add_move(&mut tree, *m);
+    }
+    return Ok(tree);
+}
+
+pub fn get_current_node(tree: &GameTree) -> &GameNode {
+    return &tree.nodes[tree.current];
+}
+
+// for setting the comment, clock and eval of the move just played
+pub fn get_current_node_mut(tree: &mut GameTree) -> &mut GameNode {
+    return &mut tree.nodes[tree.current];
+}
+
+pub fn add_nag(tree: &mut GameTree, nag: u8) {
+    let node = get_current_node_mut(tree);
+    if !node.nags.contains(&nag) {
+        node.nags.push(nag);
+    }
+}
+
+// plays m, which must be legal as for play_move. a move already in the tree is followed rather
+// than added again, otherwise it continues the line if nothing does yet or starts a variation
+pub fn add_move(tree: &mut GameTree, m: Move) -> NodeId {
+    let existing = tree.nodes[tree.current].children.iter().copied().find(|child| tree.nodes[*child].m == Some(m));
+    let node = match existing {
+        Some(child) => child,
+        None => {
+            tree.nodes.push(new_node(Some(m), Some(tree.current)));
+            let child = tree.nodes.len() - 1;
+            tree.nodes[tree.current].children.push(child);
+            child
+        }
+    };
+    play_move(&mut tree.position, m);
+    tree.current = node;
+    return node;
+}
+
+// the continuations of the current position, the main one first
+pub fn get_variations(tree: &GameTree) -> &[NodeId] {
+    return &tree.nodes[tree.current].children;
+}
+
+// plays the index-th continuation, 0 being the main one. returns false if there isn't one
+pub fn enter_variation(tree: &mut GameTree, index: usize) -> bool {
+    let child = match tree.nodes[tree.current].children.get(index) {
+        Some(child) => *child,
+        None => return false,
+    };
+    play_move(&mut tree.position, tree.nodes[child].m.unwrap());
+    tree.current = child;
+    return true;
+}
+
+// returns false at the end of the line
+pub fn go_forward(tree: &mut GameTree) -> bool {
+    return enter_variation(tree, 0);
+}
+
+// returns false at the root
+pub fn go_back(tree: &mut GameTree) -> bool {
+    let parent = match tree.nodes[tree.current].parent {
+        Some(parent) => parent,
+        None => return false,
+    };
+    undo_move(&mut tree.position);
+    tree.current = parent;
+    return true;
+}
+
+// the nodes from the root to node, both included
+pub fn get_path_to_node(tree: &GameTree, node: NodeId) -> Vec<NodeId> {
+    let mut path = vec![node];
+    while let Some(parent) = tree.nodes[*path.last().unwrap()].parent {
+        path.push(parent);
+    }
+    path.reverse();
+    return path;
+}
+
+// goes back to where the current line and node's line meet, then forward to node
+pub fn go_to_node(tree: &mut GameTree, node: NodeId) {
+    let path = get_path_to_node(tree, node);
+    while !path.contains(&tree.current) {
+        go_back(tree);
+    }
+    let from = path.iter().position(|n| *n == tree.current).unwrap();
+    for n in &path[from + 1..] {
+        play_move(&mut tree.position, tree.nodes[*n].m.unwrap());
+        tree.current = *n;
+    }
+}
+
+pub fn go_to_start(tree: &mut GameTree) {
+    while go_back(tree) {}
+}
+
+// follows the current line to its last move
+pub fn go_to_end(tree: &mut GameTree) {
+    while go_forward(tree) {}
+}
+
+pub fn is_on_main_line(tree: &GameTree, node: NodeId) -> bool {
+    let path = get_path_to_node(tree, node);
+    return path.windows(2).all(|pair| tree.nodes[pair[0]].children[0] == pair[1]);
+}
+
+// moves the variation the current node is in one place up among its parent's continuations,
+// making it the main one if it was the first variation. returns false on the main line
+pub fn promote_variation(tree: &mut GameTree) -> bool {
+    let path = get_path_to_node(tree, tree.current);
+    for pair in path.windows(2).rev() {
+        let siblings = &mut tree.nodes[pair[0]].children;
+        let index = siblings.iter().position(|child| *child == pair[1]).unwrap();
+        if index > 0 {
+            siblings.swap(index - 1, index);
+            return true;
+        }
+    }
+    return false;
+}
+
+pub fn promote_to_main_line(tree: &mut GameTree) {
+    while promote_variation(tree) {}
+}
+
+// the moves of the main line from the root, leaving out every variation
+pub fn get_main_line(tree: &GameTree) -> Vec<Move> {
+    let mut moves = vec![];
+    let mut node = ROOT_NODE;
+    while let Some(child) = tree.nodes[node].children.first() {
+        moves.push(tree.nodes[*child].m.unwrap());
+        node = *child;
+    }
+    return moves;
+}
+
+// the main line as a game record, e.g. for saving without the variations and annotations
+pub fn get_main_line_record(tree: &GameTree, outcome: GameOutcome) -> GameRecord {
+    let mut start_position = tree.position.clone();
+    for _ in 1..get_path_to_node(tree, tree.current).len() {
+        undo_move(&mut start_position);
+    }
+    return GameRecord {
+        start_fen: position_to_fen(&start_position),
+        chess960: start_position.chess960,
+        moves: get_main_line(tree),
+        outcome,
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn add_uci(tree: &mut GameTree, uci: &str) -> NodeId {
+        let m = move_from_uci(&mut tree.position, uci).unwrap();
+        return add_move(tree, m);
+    }
+
+    // the position must be the one at the current node, with a move to undo for each ply
+    fn assert_at(tree: &GameTree, expected_fen: &str) {
+        assert_eq!(position_to_fen(&tree.position), expected_fen);
+        assert_eq!(tree.position.undo_history.len(), get_path_to_node(tree, tree.current).len() - 1);
+    }
+
+    const AFTER_E5_NF3: &str = "rnbqkbnr/pppp1ppp/8/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2";
+    const AFTER_C5: &str = "rnbqkbnr/pp1ppppp/8/2p5/4P3/8/PPPP1PPP/RNBQKBNR w KQkq c6 0 2";
+
+    // 1. e4 e5 2. Nf3 with 1... c5 as a variation, returning the ids of e5, Nf3 and c5
+    fn new_sicilian_tree() -> (GameTree, NodeId, NodeId, NodeId) {
+        let mut tree = new_game_tree(position_from_fen(STARTING_FEN).unwrap());
+        add_uci(&mut tree, "e2e4");
+        let e5 = add_uci(&mut tree, "e7e5");
+        let nf3 = add_uci(&mut tree, "g1f3");
+        go_back(&mut tree);
+        go_back(&mut tree);
+        let c5 = add_uci(&mut tree, "c7c5");
+        return (tree, e5, nf3, c5);
+    }
+
+    #[test]
+    fn a_second_move_starts_a_variation() {
+        let (tree, _, nf3, c5) = new_sicilian_tree();
+        assert_at(&tree, AFTER_C5);
+        assert!(!is_on_main_line(&tree, c5));
+        assert!(is_on_main_line(&tree, nf3));
+    }
+
+    #[test]
+    fn adding_a_move_already_there_follows_it() {
+        let (mut tree, e5, _, _) = new_sicilian_tree();
+        go_back(&mut tree);
+        let node_count = tree.nodes.len();
+        assert_eq!(add_uci(&mut tree, "e7e5"), e5);
+        assert_eq!(tree.nodes.len(), node_count);
+    }
+
+    #[test]
+    fn navigation() {
+        let (mut tree, _, nf3, c5) = new_sicilian_tree();
+        go_back(&mut tree);
+        assert!(enter_variation(&mut tree, 1));
+        assert_eq!(tree.current, c5);
+        assert!(!enter_variation(&mut tree, 0));
+        assert!(!go_forward(&mut tree));
+
+        go_to_node(&mut tree, nf3);
+        assert_at(&tree, AFTER_E5_NF3);
+        go_to_start(&mut tree);
+        assert_at(&tree, STARTING_FEN);
+        assert!(!go_back(&mut tree));
+        go_to_end(&mut tree);
+        assert_eq!(tree.current, nf3);
+    }
+
+    #[test]
+    fn nags_are_added_once() {
+        let (mut tree, _, _, _) = new_sicilian_tree();
+        add_nag(&mut tree, NAG_INTERESTING_MOVE);
+        add_nag(&mut tree, NAG_INTERESTING_MOVE);
+        get_current_node_mut(&mut tree).comment = "the sicilian".to_string();
+        assert_eq!(get_current_node(&tree).nags, [NAG_INTERESTING_MOVE]);
+        assert_eq!(get_current_node(&tree).comment, "the sicilian");
+    }
+
+    #[test]
+    fn promoting_the_only_variation_makes_it_the_main_line() {
+        let (mut tree, _, _, c5) = new_sicilian_tree();
+        assert!(promote_variation(&mut tree));
+        assert!(!promote_variation(&mut tree));
+        assert!(is_on_main_line(&tree, c5));
+        let main_line: Vec<String> = get_main_line(&tree).iter().map(|m| move_to_uci(&tree.position, *m)).collect();
+        assert_eq!(main_line, ["e2e4", "c7c5"]);
+    }
+
+    // the moves from the root to the current node played on a fresh start position
+    fn replay_current_line(tree: &GameTree) -> Position {
+        let mut game_position = position_from_fen(STARTING_FEN).unwrap();
+        for node in get_path_to_node(tree, tree.current).iter().skip(1) {
+            play_move(&mut game_position, tree.nodes[*node].m.unwrap());
+        }
+        return game_position;
+    }
+
+    #[test]
+    fn going_to_a_node_in_another_variation_matches_a_replay() {
+        let (mut tree, _, nf3, c5) = new_sicilian_tree();
+        let nc3 = add_uci(&mut tree, "b1c3");
+        go_to_node(&mut tree, ROOT_NODE);
+        add_uci(&mut tree, "d2d4");
+        let nf6 = add_uci(&mut tree, "g8f6");
+        for node in [nf3, nc3, c5, nf6, ROOT_NODE, nc3] {
+            go_to_node(&mut tree, node);
+            assert_eq!(tree.current, node);
+            assert!(tree.position == replay_current_line(&tree), "at node {}", node);
+        }
+    }
+
+    #[test]
+    fn clock_and_eval_stay_with_their_node() {
+        let (mut tree, e5, _, c5) = new_sicilian_tree();
+        get_current_node_mut(&mut tree).clock = Some(Duration::from_secs(170));
+        get_current_node_mut(&mut tree).eval = Some(Evaluation::Centipawns(35));
+        go_to_node(&mut tree, e5);
+        get_current_node_mut(&mut tree).eval = Some(Evaluation::MateIn(-12));
+        assert_eq!(get_current_node(&tree).clock, None);
+
+        go_to_node(&mut tree, c5);
+        assert_eq!(get_current_node(&tree).clock, Some(Duration::from_secs(170)));
+        assert_eq!(get_current_node(&tree).eval, Some(Evaluation::Centipawns(35)));
+        assert_eq!(tree.nodes[e5].eval, Some(Evaluation::MateIn(-12)));
+    }
+
+    // 1. e4 e5 (1... c5 2. Nf3 (2. Nc3 d6)), promoting d6 twice over
+    #[test]
+    fn promoting_a_nested_variation_to_the_main_line() {
+        let (mut tree, e5, _, c5) = new_sicilian_tree();
+        let nf3 = add_uci(&mut tree, "g1f3");
+        go_back(&mut tree);
+        let nc3 = add_uci(&mut tree, "b1c3");
+        let d6 = add_uci(&mut tree, "d7d6");
+        let fen = position_to_fen(&tree.position);
+
+        promote_to_main_line(&mut tree);
+        assert_eq!(tree.current, d6);
+        assert_eq!(position_to_fen(&tree.position), fen);
+        assert!(is_on_main_line(&tree, d6));
+        let main_line: Vec<String> = get_main_line(&tree).iter().map(|m| move_to_uci(&tree.position, *m)).collect();
+        assert_eq!(main_line, ["e2e4", "c7c5", "b1c3", "d7d6"]);
+        // the lines they replaced become their first variations
+        assert_eq!(tree.nodes[tree.nodes[c5].parent.unwrap()].children, [c5, e5]);
+        assert_eq!(tree.nodes[c5].children, [nc3, nf3]);
+    }
+
+    #[test]
+    fn main_line_record_leaves_out_the_variations() {
+        let (mut tree, _, nf3, _) = new_sicilian_tree();
+        let record = get_main_line_record(&tree, GameOutcome::Ongoing);
+        assert_eq!(record.start_fen, STARTING_FEN);
+        assert!(!record.chess960);
+        let moves: Vec<String> = record.moves.iter().map(|m| move_to_uci(&tree.position, *m)).collect();
+        assert_eq!(moves, ["e2e4", "e7e5", "g1f3"]);
+
+        // and the record reads back as a tree of just the main line, ending at its last move
+        let from_record = game_tree_from_record(&record).unwrap();
+        assert_eq!(from_record.nodes.len(), 4);
+        assert_at(&from_record, AFTER_E5_NF3);
+        go_to_node(&mut tree, nf3);
+        assert!(from_record.position == tree.position);
+    }
+
+    // the root is wherever the tree was started, not the start of the position's own history
+    #[test]
+    fn main_line_record_starts_at_the_root() {
+        let mut start_position = empty_position();
+        initialise_chess960_board(&mut start_position, 0);
+        let m = move_from_uci(&mut start_position, "e2e4").unwrap();
+        play_move(&mut start_position, m);
+        let root_fen = position_to_fen(&start_position);
+
+        let mut tree = new_game_tree(start_position);
+        add_uci(&mut tree, "e7e5");
+        let record = get_main_line_record(&tree, GameOutcome::Ongoing);
+        assert_eq!(record.start_fen, root_fen);
+        assert!(record.chess960);
+        assert_eq!(record.moves.len(), 1);
+        assert!(game_tree_from_record(&record).unwrap().position.chess960);
+    }
+}
@@ -0,0 +1,13 @@
+// the rules, engine and notation, with no graphics dependency
+pub mod bitboard;
+pub mod board;
+pub mod engine;
+pub mod game_tree;
+pub mod notation;
+pub mod perft;
+pub mod record;
+pub mod rules;
+#[cfg(feature = "serde")]
+mod serialization;
+pub mod square;
+pub mod zobrist;
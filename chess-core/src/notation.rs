@@ -0,0 +1,721 @@
+use std::fmt;
+use crate::board::*;
+use crate::rules::*;
+use crate::bitboard::*;
+use crate::square::*;
+use crate::zobrist::*;
+
+pub const STARTING_FEN: &str = "rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FenError {
+    WrongFieldCount(usize),
+    WrongRankCount(usize),
+    WrongRankLength(usize),
+    InvalidPiece(char),
+    InvalidTurn(String),
+    InvalidCastling(String),
+    InvalidEnPassant(String),
+    InvalidHalfmoveClock(String),
+    InvalidFullmoveNumber(String),
+    InvalidPosition(Vec<PositionProblem>),
+}
+
+impl fmt::Display for FenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FenError::WrongFieldCount(n) => write!(f, "expected 4 or 6 fields, found {}", n),
+            FenError::WrongRankCount(n) => write!(f, "expected 8 ranks, found {}", n),
+            FenError::WrongRankLength(rank) => write!(f, "rank {} does not have 8 squares", rank),
+            FenError::InvalidPiece(c) => write!(f, "invalid piece '{}'", c),
+            FenError::InvalidTurn(s) => write!(f, "invalid side to move '{}'", s),
+            FenError::InvalidCastling(s) => write!(f, "invalid castling rights '{}'", s),
+            FenError::InvalidEnPassant(s) => write!(f, "invalid en passant square '{}'", s),
+            FenError::InvalidHalfmoveClock(s) => write!(f, "invalid halfmove clock '{}'", s),
+            FenError::InvalidFullmoveNumber(s) => write!(f, "invalid fullmove number '{}'", s),
+            FenError::InvalidPosition(problems) => {
+                let problems: Vec<String> = problems.iter().map(|problem| problem.to_string()).collect();
+                write!(f, "impossible position: {}", problems.join(", "))
+            }
+        }
+    }
+}
+
+impl std::error::Error for FenError {}
+
+// e.g. ('e', '2') -> e2
+fn parse_square(file: char, rank: char) -> Option<Square> {
+    return Some(make_square(file_from_char(file)?, rank_from_char(rank)?));
+}
+
+// UCI coordinate notation, e.g. "e2e4" or "e7e8q". castling is written as the king's move,
+// e.g. "e1g1", except in Chess960 where it is written as the king taking its own rook
+pub fn move_to_uci(game_position: &Position, m: Move) -> String {
+    return get_uci(m, game_position.chess960);
+}
+
+fn get_uci(m: Move, chess960: bool) -> String {
+    let mut end = m.end;
+    if is_castling_move(m) && !chess960 {
+        end = make_square(get_castling_destination_files(m).0, get_square_rank(m.end));
+    }
+    let mut uci = get_square_name(m.start) + &get_square_name(end);
+    if m.promotion != BLANK_PIECE {
+        uci.push(piece_to_fen_char(m.promotion).to_ascii_lowercase());
+    }
+    return uci;
+}
+
+// without a position castling can only be written the classical way, so Chess960 moves
+// should go through move_to_uci
+impl fmt::Display for Move {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", get_uci(*self, false))
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum UciError {
+    InvalidSyntax(String),
+    IllegalMove(String),
+}
+
+impl fmt::Display for UciError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            UciError::InvalidSyntax(s) => write!(f, "'{}' is not a move in UCI notation", s),
+            UciError::IllegalMove(s) => write!(f, "'{}' is not a legal move", s),
+        }
+    }
+}
+
+impl std::error::Error for UciError {}
+
+// the legal move written in UCI notation. outside Chess960 castling may also be given as
+// the king taking its own rook
+pub fn move_from_uci(game_position: &mut Position, uci: &str) -> Result<Move, UciError> {
+    let chars: Vec<char> = uci.chars().collect();
+    if chars.len() != 4 && chars.len() != 5 {
+        return Err(UciError::InvalidSyntax(uci.to_string()));
+    }
+    let start = parse_square(chars[0], chars[1]);
+    let end = parse_square(chars[2], chars[3]);
+    let promotion_type = match chars.get(4) {
+        None => PieceTypes::BLANK,
+        Some('q') => PieceTypes::QUEEN,
+        Some('r') => PieceTypes::ROOK,
+        Some('b') => PieceTypes::BISHOP,
+        Some('n') => PieceTypes::KNIGHT,
+        Some(_) => return Err(UciError::InvalidSyntax(uci.to_string())),
+    };
+    let (start, end) = match (start, end) {
+        (Some(start), Some(end)) => (start, end),
+        _ => return Err(UciError::InvalidSyntax(uci.to_string())),
+    };
+
+    let chess960 = game_position.chess960;
+    return get_all_legal_moves(game_position).into_iter().find(|m| {
+        let end_matches = if is_castling_move(*m) {
+            m.end == end || (!chess960 && make_square(get_castling_destination_files(*m).0, get_square_rank(m.end)) == end)
+        } else {
+            m.end == end
+        };
+        m.start == start && end_matches && m.promotion.piece_type == promotion_type
+    }).ok_or(UciError::IllegalMove(uci.to_string()));
+}
+
+pub fn piece_from_fen_char(c: char) -> Option<Piece> {
+    let piece = match c {
+        'K' => WHITE_KING_PIECE,
+        'Q' => WHITE_QUEEN_PIECE,
+        'R' => WHITE_ROOK_PIECE,
+        'N' => WHITE_KNIGHT_PIECE,
+        'B' => WHITE_BISHOP_PIECE,
+        'P' => WHITE_PAWN_PIECE,
+        'k' => BLACK_KING_PIECE,
+        'q' => BLACK_QUEEN_PIECE,
+        'r' => BLACK_ROOK_PIECE,
+        'n' => BLACK_KNIGHT_PIECE,
+        'b' => BLACK_BISHOP_PIECE,
+        'p' => BLACK_PAWN_PIECE,
+        _ => return None,
+    };
+    return Some(piece);
+}
+
+pub fn piece_to_fen_char(piece: Piece) -> char {
+    let c = match piece.piece_type {
+        PieceTypes::KING => 'k',
+        PieceTypes::QUEEN => 'q',
+        PieceTypes::ROOK => 'r',
+        PieceTypes::KNIGHT => 'n',
+        PieceTypes::BISHOP => 'b',
+        PieceTypes::PAWN => 'p',
+        PieceTypes::BLANK => '.',
+    };
+    if piece.colour == Colours::WHITE {
+        return c.to_ascii_uppercase();
+    }
+    return c;
+}
+
+// classical FEN, where KQkq always means the rooks on the a and h files, even if they have
+// moved. the position is only Chess960 if the castling field names rook files (Shredder-FEN)
+pub fn position_from_fen(fen: &str) -> Result<Position, FenError> {
+    return parse_fen(fen, false);
+}
+
+// X-FEN or Shredder-FEN for a Chess960 position, where K and Q mean the outermost rook on
+// that side of the king
+pub fn position_from_chess960_fen(fen: &str) -> Result<Position, FenError> {
+    return parse_fen(fen, true);
+}
+
+fn parse_fen(fen: &str, chess960: bool) -> Result<Position, FenError> {
+    let fields: Vec<&str> = fen.split_whitespace().collect();
+    if fields.len() != 4 && fields.len() != 6 {
+        return Err(FenError::WrongFieldCount(fields.len()));
+    }
+    let mut game_position = empty_position();
+
+    // piece placement, from rank 8 down to rank 1
+    let ranks: Vec<&str> = fields[0].split('/').collect();
+    if ranks.len() != 8 {
+        return Err(FenError::WrongRankCount(ranks.len()));
+    }
+    for (i, rank_field) in ranks.iter().enumerate() {
+        let y = 7 - i;
+        let rank = rank_from_index(y).unwrap();
+        let mut x = 0;
+        for c in rank_field.chars() {
+            if let Some(empty_squares) = c.to_digit(10) {
+                if empty_squares == 0 || empty_squares > 8 {
+                    return Err(FenError::WrongRankLength(y + 1));
+                }
+                x += empty_squares as usize;
+            } else {
+                let piece = piece_from_fen_char(c).ok_or(FenError::InvalidPiece(c))?;
+                let file = file_from_index(x).ok_or(FenError::WrongRankLength(y + 1))?;
+                set_piece(&mut game_position, make_square(file, rank), piece);
+                x += 1;
+            }
+            if x > 8 {
+                return Err(FenError::WrongRankLength(y + 1));
+            }
+        }
+        if x != 8 {
+            return Err(FenError::WrongRankLength(y + 1));
+        }
+    }
+
+    game_position.turn = match fields[1] {
+        "w" => 1,
+        "b" => -1,
+        _ => return Err(FenError::InvalidTurn(fields[1].to_string())),
+    };
+
+    // KQkq, or the file of the castling rook for Chess960 (Shredder-FEN, e.g. HAha),
+    // which X-FEN only uses when K or Q would not pick out the outermost rook
+    let mut uses_rook_files = false;
+    if fields[2] != "-" {
+        for c in fields[2].chars() {
+            let colour = if c.is_ascii_uppercase() { Colours::WHITE } else { Colours::BLACK };
+            let king_file = get_home_rank_king_file(&game_position, colour);
+            let (right, rook_file) = match c.to_ascii_lowercase() {
+                'k' if chess960 => (get_kingside_right(colour), get_outermost_rook_file(&game_position, colour, true).unwrap_or(FILE_H)),
+                'q' if chess960 => (get_queenside_right(colour), get_outermost_rook_file(&game_position, colour, false).unwrap_or(FILE_A)),
+                'k' => (get_kingside_right(colour), FILE_H),
+                'q' => (get_queenside_right(colour), FILE_A),
+                'a'..='h' => {
+                    let rook_file = file_from_char(c.to_ascii_lowercase()).unwrap();
+                    let king_file = king_file.ok_or(FenError::InvalidCastling(fields[2].to_string()))?;
+                    uses_rook_files = true;
+                    if rook_file > king_file {
+                        (get_kingside_right(colour), rook_file)
+                    } else {
+                        (get_queenside_right(colour), rook_file)
+                    }
+                }
+                _ => return Err(FenError::InvalidCastling(fields[2].to_string())),
+            };
+            if has_castling_right(&game_position, right) {
+                return Err(FenError::InvalidCastling(fields[2].to_string()));
+            }
+            game_position.castling_rights |= right;
+            game_position.castling_rook_files[right.trailing_zeros() as usize] = rook_file;
+        }
+    }
+    game_position.chess960 = chess960 || uses_rook_files;
+
+    if fields[3] != "-" {
+        let expected_rank = if game_position.turn == 1 { RANK_6 } else { RANK_3 };
+        let sq = square_from_name(fields[3]).filter(|sq| get_square_rank(*sq) == expected_rank);
+        let sq = sq.ok_or(FenError::InvalidEnPassant(fields[3].to_string()))?;
+        game_position.en_passant = Some(get_square_file(sq));
+    }
+
+    if fields.len() == 6 {
+        game_position.halfmove_clock = fields[4].parse()
+            .map_err(|_| FenError::InvalidHalfmoveClock(fields[4].to_string()))?;
+        game_position.fullmove_number = match fields[5].parse() {
+            Ok(n) if n >= 1 => n,
+            _ => return Err(FenError::InvalidFullmoveNumber(fields[5].to_string())),
+        };
+    }
+
+    let problems = validate_position(&game_position);
+    if !problems.is_empty() {
+        return Err(FenError::InvalidPosition(problems));
+    }
+
+    game_position.zobrist_key = compute_zobrist_key(&game_position);
+    return Ok(game_position);
+}
+
+fn get_home_rank_king_file(game_position: &Position, colour: Colours) -> Option<File> {
+    let home_rank = if colour == Colours::WHITE { RANK_1 } else { RANK_8 };
+    let king = if colour == Colours::WHITE { WHITE_KING_PIECE } else { BLACK_KING_PIECE };
+    return all_files().find(|file| get_piece_on(game_position, make_square(*file, home_rank)) == king);
+}
+
+// the rook furthest from the king on the given side of it, on the home rank
+fn get_outermost_rook_file(game_position: &Position, colour: Colours, kingside: bool) -> Option<File> {
+    let home_rank = if colour == Colours::WHITE { RANK_1 } else { RANK_8 };
+    let rook = if colour == Colours::WHITE { WHITE_ROOK_PIECE } else { BLACK_ROOK_PIECE };
+    let king_file = get_home_rank_king_file(game_position, colour)?;
+    let is_rook = |file: &File| get_piece_on(game_position, make_square(*file, home_rank)) == rook;
+    if kingside {
+        return all_files().rev().take_while(|file| *file > king_file).find(is_rook);
+    }
+    return all_files().take_while(|file| *file < king_file).find(is_rook);
+}
+
+// KQkq for classical chess, for Chess960 X-FEN or, if shredder is set, Shredder-FEN
+fn get_castling_field(game_position: &Position, shredder: bool) -> String {
+    let mut castling = String::new();
+    for (right, c) in [(WHITE_KINGSIDE, 'K'), (WHITE_QUEENSIDE, 'Q'), (BLACK_KINGSIDE, 'k'), (BLACK_QUEENSIDE, 'q')] {
+        if !has_castling_right(game_position, right) {
+            continue;
+        }
+        let colour = if c.is_ascii_uppercase() { Colours::WHITE } else { Colours::BLACK };
+        let rook_file = get_castling_rook_file(game_position, right);
+        // position_from_fen reads K and Q as the classical h and a rooks, so any other Chess960
+        // right is written as its rook file, which also marks the FEN as Chess960
+        let classical_rook_file = if c.eq_ignore_ascii_case(&'k') { FILE_H } else { FILE_A };
+        let is_classical = get_home_rank_king_file(game_position, colour) == Some(FILE_E) && rook_file == classical_rook_file;
+        if !game_position.chess960 || (!shredder && is_classical) {
+            castling.push(c);
+            continue;
+        }
+        let file = get_file_char(rook_file);
+        castling.push(if colour == Colours::WHITE { file.to_ascii_uppercase() } else { file });
+    }
+    if castling.is_empty() {
+        castling.push('-');
+    }
+    return castling;
+}
+
+// X-FEN, the same as FEN except for Chess960 castling rights that don't use the e-file king and
+// the a or h rook, which are given as rook files
+pub fn position_to_fen(game_position: &Position) -> String {
+    return get_fen(game_position, false);
+}
+
+// castling rights are always given as rook files for Chess960
+pub fn position_to_shredder_fen(game_position: &Position) -> String {
+    return get_fen(game_position, true);
+}
+
+fn get_fen(game_position: &Position, shredder: bool) -> String {
+    let mut fen = String::new();
+
+    for rank in all_ranks().rev() {
+        let mut empty_squares = 0;
+        for file in all_files() {
+            let piece = get_piece_on(game_position, make_square(file, rank));
+            if piece.piece_type == PieceTypes::BLANK {
+                empty_squares += 1;
+                continue;
+            }
+            if empty_squares > 0 {
+                fen.push_str(&empty_squares.to_string());
+                empty_squares = 0;
+            }
+            fen.push(piece_to_fen_char(piece));
+        }
+        if empty_squares > 0 {
+            fen.push_str(&empty_squares.to_string());
+        }
+        if rank != RANK_1 {
+            fen.push('/');
+        }
+    }
+
+    fen.push_str(if game_position.turn == 1 { " w " } else { " b " });
+
+    fen.push_str(&get_castling_field(game_position, shredder));
+
+    if let Some(file) = game_position.en_passant {
+        fen.push(' ');
+        fen.push(get_file_char(file));
+        fen.push(if game_position.turn == 1 { '6' } else { '3' });
+    } else {
+        fen.push_str(" -");
+    }
+
+    fen.push_str(&format!(" {} {}", game_position.halfmove_clock, game_position.fullmove_number));
+    return fen;
+}
+
+/////////////////////////////////////////////////////////////////
+// standard algebraic notation
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SanError {
+    InvalidSyntax(String),
+    IllegalMove(String),
+    AmbiguousMove(String),
+}
+
+impl fmt::Display for SanError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            SanError::InvalidSyntax(s) => write!(f, "'{}' is not a move in SAN", s),
+            SanError::IllegalMove(s) => write!(f, "'{}' is not a legal move", s),
+            SanError::AmbiguousMove(s) => write!(f, "'{}' could be more than one legal move", s),
+        }
+    }
+}
+
+impl std::error::Error for SanError {}
+
+// the file and/or rank of the start square needed to tell m apart from the other legal
+// moves of the same piece type to the same square
+fn get_san_disambiguation(legal_moves: &[Move], m: Move) -> String {
+    let others: Vec<&Move> = legal_moves.iter().filter(|other| {
+        other.start_piece == m.start_piece && other.end == m.end && other.start != m.start && !is_castling_move(**other)
+    }).collect();
+    if others.is_empty() {
+        return String::new();
+    }
+    let (file, rank) = (get_square_file(m.start), get_square_rank(m.start));
+    if others.iter().all(|other| get_square_file(other.start) != file) {
+        return file.to_string();
+    }
+    if others.iter().all(|other| get_square_rank(other.start) != rank) {
+        return rank.to_string();
+    }
+    return get_square_name(m.start);
+}
+
+// e.g. "Nbd7", "exd6", "e8=Q+", "O-O-O" or "Qh4#". m must be legal in game_position
+pub fn move_to_san(game_position: &mut Position, m: Move) -> String {
+    let mut san = String::new();
+    if is_castling_move(m) {
+        san.push_str(if get_square_file(m.end) > get_square_file(m.start) { "O-O" } else { "O-O-O" });
+    } else if m.start_piece.piece_type == PieceTypes::PAWN {
+        if is_capture_move(m) {
+            san.push(get_file_char(get_square_file(m.start)));
+            san.push('x');
+        }
+        san.push_str(&get_square_name(m.end));
+        if m.promotion != BLANK_PIECE {
+            san.push('=');
+            san.push(piece_to_fen_char(m.promotion).to_ascii_uppercase());
+        }
+    } else {
+        let legal_moves = get_all_legal_moves(game_position);
+        san.push(piece_to_fen_char(m.start_piece).to_ascii_uppercase());
+        san.push_str(&get_san_disambiguation(&legal_moves, m));
+        if is_capture_move(m) {
+            san.push('x');
+        }
+        san.push_str(&get_square_name(m.end));
+    }
+
+    play_move(game_position, m);
+    if is_in_check(game_position) {
+        san.push(if get_all_legal_moves(game_position).is_empty() { '#' } else { '+' });
+    }
+    undo_move(game_position);
+    return san;
+}
+
+// the legal move written in SAN. check and mate markers and annotations such as "!?" are
+// ignored, as is a missing "x", but a piece that doesn't say which of several it is is an error
+pub fn move_from_san(game_position: &mut Position, san: &str) -> Result<Move, SanError> {
+    let invalid = || SanError::InvalidSyntax(san.to_string());
+    let trimmed = san.trim().trim_end_matches(['+', '#', '!', '?']);
+    let legal_moves = get_all_legal_moves(game_position);
+
+    // castling, with letter O or the digit 0
+    let castling = trimmed.replace('0', "O");
+    if castling == "O-O" || castling == "O-O-O" {
+        let kingside = castling == "O-O";
+        return legal_moves.into_iter()
+            .find(|m| is_castling_move(*m) && (get_square_file(m.end) > get_square_file(m.start)) == kingside)
+            .ok_or(SanError::IllegalMove(san.to_string()));
+    }
+
+    let mut chars: Vec<char> = trimmed.chars().collect();
+
+    // the piece type, pawns have none
+    let piece_type = match chars.first() {
+        Some('K') => PieceTypes::KING,
+        Some('Q') => PieceTypes::QUEEN,
+        Some('R') => PieceTypes::ROOK,
+        Some('B') => PieceTypes::BISHOP,
+        Some('N') => PieceTypes::KNIGHT,
+        Some('a'..='h') => PieceTypes::PAWN,
+        _ => return Err(invalid()),
+    };
+    if piece_type != PieceTypes::PAWN {
+        chars.remove(0);
+    }
+
+    // promotion, as "e8=Q" or "e8Q"
+    let mut promotion_type = PieceTypes::BLANK;
+    if piece_type == PieceTypes::PAWN && chars.len() > 2 {
+        let last = chars[chars.len() - 1];
+        promotion_type = match last {
+            'Q' => PieceTypes::QUEEN,
+            'R' => PieceTypes::ROOK,
+            'B' => PieceTypes::BISHOP,
+            'N' => PieceTypes::KNIGHT,
+            _ => PieceTypes::BLANK,
+        };
+        if promotion_type != PieceTypes::BLANK {
+            chars.pop();
+            if chars.last() == Some(&'=') {
+                chars.pop();
+            }
+        }
+    }
+
+    // the destination square is always last
+    if chars.len() < 2 {
+        return Err(invalid());
+    }
+    let end = parse_square(chars[chars.len() - 2], chars[chars.len() - 1]).ok_or(invalid())?;
+    chars.truncate(chars.len() - 2);
+
+    // then an optional "x", and before that the start file and/or rank
+    let mut capture = chars.last() == Some(&'x');
+    if capture {
+        chars.pop();
+    }
+    let mut start_file = None;
+    let mut start_rank = None;
+    for c in chars {
+        match c {
+            'a'..='h' if start_file.is_none() && start_rank.is_none() => start_file = file_from_char(c),
+            '1'..='8' if start_rank.is_none() => start_rank = rank_from_char(c),
+            _ => return Err(invalid()),
+        }
+    }
+    // a pawn capture always names the file it came from, and a pawn move nothing else, so a
+    // pawn with a file is a capture even without the "x"
+    if piece_type == PieceTypes::PAWN {
+        if start_rank.is_some() {
+            return Err(invalid());
+        }
+        capture = start_file.is_some();
+    }
+
+    let candidates: Vec<Move> = legal_moves.into_iter().filter(|m| {
+        m.start_piece.piece_type == piece_type && !is_castling_move(*m)
+            && m.end == end
+            && m.promotion.piece_type == promotion_type
+            && start_file.is_none_or(|file| get_square_file(m.start) == file)
+            && start_rank.is_none_or(|rank| get_square_rank(m.start) == rank)
+            && (!capture || is_capture_move(*m))
+    }).collect();
+    return match candidates.len() {
+        0 => Err(SanError::IllegalMove(san.to_string())),
+        1 => Ok(candidates[0]),
+        _ => Err(SanError::AmbiguousMove(san.to_string())),
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // m written in SAN, which must also read back as m
+    fn get_san(fen: &str, uci: &str) -> String {
+        let mut game_position = position_from_fen(fen).unwrap();
+        let m = move_from_uci(&mut game_position, uci).unwrap();
+        let san = move_to_san(&mut game_position, m);
+        let parsed = move_from_san(&mut game_position, &san).unwrap();
+        assert_eq!(move_to_uci(&game_position, parsed), uci, "{} read back from {}", uci, san);
+        return san;
+    }
+
+    fn get_san_error(fen: &str, san: &str) -> SanError {
+        let mut game_position = position_from_fen(fen).unwrap();
+        return match move_from_san(&mut game_position, san) {
+            Ok(m) => panic!("{} read as {}", san, move_to_uci(&game_position, m)),
+            Err(e) => e,
+        };
+    }
+
+    // the legal move the UCI is read as, written back in UCI
+    fn read_uci(fen: &str, uci: &str) -> Option<String> {
+        let mut game_position = position_from_fen(fen).unwrap();
+        return move_from_uci(&mut game_position, uci).ok().map(|m| move_to_uci(&game_position, m));
+    }
+
+    #[test]
+    fn fen_round_trip() {
+        for fen in [STARTING_FEN, "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1"] {
+            assert_eq!(position_to_fen(&position_from_fen(fen).unwrap()), fen);
+        }
+    }
+
+    // castling rights whose king and rook aren't there are kept as they are, rather than
+    // being taken as Chess960 ones
+    #[test]
+    fn fen_keeps_classical_rights_without_their_pieces() {
+        let game_position = position_from_fen("k7/8/8/8/8/8/8/7K w KQkq - 0 1").unwrap();
+        assert!(!game_position.chess960);
+        assert_eq!(position_to_fen(&game_position), "k7/8/8/8/8/8/8/7K w KQkq - 0 1");
+
+        // the b1 rook is not the queenside castling rook
+        let mut game_position = position_from_fen("4k3/8/8/8/8/8/8/1R2K2R w KQ - 0 1").unwrap();
+        assert!(!game_position.chess960);
+        assert_eq!(get_castling_rook_file(&game_position, WHITE_QUEENSIDE), FILE_A);
+        assert_eq!(position_to_fen(&game_position), "4k3/8/8/8/8/8/8/1R2K2R w KQ - 0 1");
+        let castling: Vec<String> = get_all_legal_moves(&mut game_position).into_iter()
+            .filter(|m| is_castling_move(*m))
+            .map(|m| move_to_uci(&game_position, m))
+            .collect();
+        assert_eq!(castling, ["e1g1"]);
+    }
+
+    #[test]
+    fn fen_with_rook_files_is_chess960() {
+        let game_position = position_from_fen("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1").unwrap();
+        assert!(game_position.chess960);
+        assert_eq!(get_castling_rook_file(&game_position, WHITE_QUEENSIDE), FILE_B);
+        assert_eq!(position_to_fen(&game_position), "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1");
+        assert_eq!(position_to_shredder_fen(&game_position), "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1");
+    }
+
+    // X-FEN's KQkq only picks out the outermost rooks when asked to read Chess960
+    #[test]
+    fn chess960_fen() {
+        let game_position = position_from_chess960_fen("1r4kr/8/8/8/8/8/8/1R4KR w KQkq - 0 1").unwrap();
+        assert!(game_position.chess960);
+        assert_eq!(position_to_shredder_fen(&game_position), "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1");
+        assert_eq!(position_to_fen(&game_position), "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1");
+    }
+
+    // reading back a Chess960 position's FEN keeps it Chess960 with the same castling moves
+    #[test]
+    fn chess960_fen_round_trip() {
+        let mut game_position = position_from_fen("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1").unwrap();
+        let fen = position_to_fen(&game_position);
+        let mut read_back = position_from_fen(&fen).unwrap();
+        assert!(read_back.chess960);
+        assert_eq!(position_to_fen(&read_back), fen);
+        let get_castling = |game_position: &mut Position| -> Vec<String> {
+            return get_all_legal_moves(game_position).into_iter()
+                .filter(|m| is_castling_move(*m))
+                .map(|m| move_to_uci(game_position, m))
+                .collect();
+        };
+        assert_eq!(get_castling(&mut read_back), get_castling(&mut game_position));
+        assert_eq!(get_castling(&mut read_back).len(), 2);
+    }
+
+    #[test]
+    fn san_of_simple_moves() {
+        assert_eq!(get_san(STARTING_FEN, "e2e4"), "e4");
+        assert_eq!(get_san(STARTING_FEN, "g1f3"), "Nf3");
+    }
+
+    #[test]
+    fn san_of_pawn_captures() {
+        assert_eq!(get_san("rnbqkbnr/ppp1pppp/8/3p4/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 2", "e4d5"), "exd5");
+        assert_eq!(get_san("rnbqkbnr/ppp1p1pp/8/3pPp2/8/8/PPPP1PPP/RNBQKBNR w KQkq f6 0 3", "e5f6"), "exf6");
+    }
+
+    // pieces that can reach the same square are told apart by file, then by rank, then both
+    #[test]
+    fn san_disambiguation() {
+        assert_eq!(get_san("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "b1d2"), "Nbd2");
+        assert_eq!(get_san("4k3/8/8/8/8/5N2/8/4KN2 w - - 0 1", "f3d2"), "N3d2");
+        assert_eq!(get_san("4k3/8/8/8/8/1Q3Q2/8/1Q2K3 w - - 0 1", "b3d3"), "Qb3d3");
+    }
+
+    // only moves that are legal count, the pinned knight on d2 needs no telling apart
+    #[test]
+    fn san_disambiguation_ignores_pinned_pieces() {
+        assert_eq!(get_san("4k3/8/8/b7/8/8/3N4/4K1N1 w - - 0 1", "g1f3"), "Nf3");
+    }
+
+    #[test]
+    fn san_of_promotions() {
+        assert_eq!(get_san("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8n"), "b8=N");
+        assert_eq!(get_san("r3k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7a8q"), "bxa8=Q+");
+    }
+
+    #[test]
+    fn san_of_castling() {
+        assert_eq!(get_san("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1c1"), "O-O-O");
+        assert_eq!(get_san("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", "g1h1"), "O-O");
+    }
+
+    #[test]
+    fn san_of_mates() {
+        assert_eq!(get_san("rnbqkbnr/pppp1ppp/8/4p3/6P1/5P2/PPPPP2P/RNBQKBNR b KQkq - 0 2", "d8h4"), "Qh4#");
+        assert_eq!(get_san("6k1/5ppp/8/8/8/8/8/R3K3 w Q - 0 1", "a1a8"), "Ra8#");
+    }
+
+    #[test]
+    fn san_without_the_capture_mark() {
+        let mut game_position = position_from_fen("rnbqkbnr/ppp2ppp/3p4/3p4/2N1P3/8/PPPP1PPP/R1BQKBNR w KQkq - 0 3").unwrap();
+        for (san, uci) in [("ed5", "e4d5"), ("exd5", "e4d5"), ("Nd6", "c4d6"), ("Nxd6", "c4d6")] {
+            let m = move_from_san(&mut game_position, san).unwrap();
+            assert_eq!(move_to_uci(&game_position, m), uci, "{}", san);
+        }
+        // a file on a pawn move that isn't a capture is still wrong
+        assert_eq!(get_san_error(STARTING_FEN, "ee4"), SanError::IllegalMove("ee4".to_string()));
+    }
+
+    #[test]
+    fn san_errors() {
+        assert_eq!(get_san_error(STARTING_FEN, "e5"), SanError::IllegalMove("e5".to_string()));
+        assert_eq!(get_san_error(STARTING_FEN, "Nd2"), SanError::IllegalMove("Nd2".to_string()));
+        assert_eq!(get_san_error(STARTING_FEN, "O-O"), SanError::IllegalMove("O-O".to_string()));
+        assert_eq!(get_san_error(STARTING_FEN, "Zf3"), SanError::InvalidSyntax("Zf3".to_string()));
+        assert_eq!(get_san_error(STARTING_FEN, "e4e5"), SanError::InvalidSyntax("e4e5".to_string()));
+        assert_eq!(get_san_error("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", "Nd2"), SanError::AmbiguousMove("Nd2".to_string()));
+        // a promotion has to say what to
+        assert_eq!(get_san_error("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b8"), SanError::IllegalMove("b8".to_string()));
+    }
+
+    #[test]
+    fn uci_moves() {
+        assert_eq!(read_uci(STARTING_FEN, "e2e4").as_deref(), Some("e2e4"));
+        assert_eq!(read_uci(STARTING_FEN, "e2e5"), None);
+        assert_eq!(read_uci(STARTING_FEN, "e2"), None);
+        assert_eq!(read_uci(STARTING_FEN, "i2i4"), None);
+    }
+
+    #[test]
+    fn uci_promotions() {
+        assert_eq!(read_uci("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8r").as_deref(), Some("b7b8r"));
+        assert_eq!(read_uci("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8"), None);
+        assert_eq!(read_uci("4k3/1P6/8/8/8/8/8/4K3 w - - 0 1", "b7b8k"), None);
+    }
+
+    // outside Chess960 the king may also be said to take its rook
+    #[test]
+    fn uci_castling() {
+        assert_eq!(read_uci("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", "e1h1").as_deref(), Some("e1g1"));
+        assert_eq!(read_uci("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", "g1h1").as_deref(), Some("g1h1"));
+        assert_eq!(read_uci("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", "g1c1"), None);
+    }
+}
@@ -0,0 +1,120 @@
+use crate::board::*;
+use crate::rules::*;
+
+pub fn perft(game_position: &mut Position, depth: usize) -> u64 {
+    if depth == 0 {
+        return 1;
+    }
+    let mut move_list = new_move_list();
+    generate_all_legal_moves(game_position, &mut move_list);
+    if depth == 1 {
+        return move_list.len as u64;
+    }
+    let mut nodes = 0;
+    for cm in get_moves(&move_list) {
+        play_move(game_position, unpack_move(game_position, *cm));
+        nodes += perft(game_position, depth - 1);
+        undo_move(game_position);
+    }
+    return nodes;
+}
+
+// the node count below each root move, for finding which move a wrong count comes from
+pub fn divide(game_position: &mut Position, depth: usize) -> Vec<(Move, u64)> {
+    let mut counts = vec![];
+    for m in get_all_legal_moves(game_position) {
+        play_move(game_position, m);
+        let move_nodes = if depth > 1 { perft(game_position, depth - 1) } else { 1 };
+        undo_move(game_position);
+        counts.push((m, move_nodes));
+    }
+    return counts;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::*;
+
+    // fen and the expected node counts for depth 1, 2, 3, ...
+    // from https://www.chessprogramming.org/Perft_Results, Martin Sedlak's perft suite and
+    // https://www.chessprogramming.org/Chess960_Perft_Results
+    type PerftCase = (&'static str, &'static [u64]);
+
+    // depths with more nodes than this are left to the ignored deep test, run it with
+    // cargo test --release -- --ignored
+    const QUICK_NODES: u64 = 100_000;
+
+    fn assert_perft(case: PerftCase, max_nodes: u64) {
+        let (fen, expected_counts) = case;
+        let mut game_position = position_from_fen(fen).unwrap();
+        for (i, expected) in expected_counts.iter().enumerate() {
+            if *expected > max_nodes {
+                break;
+            }
+            assert_eq!(perft(&mut game_position, i + 1), *expected, "{} depth {}", fen, i + 1);
+        }
+    }
+
+    const CASES: &[PerftCase] = &[
+        (STARTING_FEN, &[20, 400, 8902, 197281, 4865609]),
+        // kiwipete
+        ("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1", &[48, 2039, 97862, 4085603]),
+        // en passant and pins along the rank
+        ("8/2p5/3p4/KP5r/1R3p1k/8/4P1P1/8 w - - 0 1", &[14, 191, 2812, 43238, 674624]),
+        // promotions and castling under attack
+        ("r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1", &[6, 264, 9467, 422333]),
+        // position 5
+        ("rnbq1k1r/pp1Pbppp/2p5/8/2B5/8/PPP1NnPP/RNBQK2R w KQ - 1 8", &[44, 1486, 62379, 2103487]),
+        // en passant that would expose the king
+        ("3k4/3p4/8/K1P4r/8/8/8/8 b - - 0 1", &[18, 92, 1670, 10138, 185429, 1134888]),
+        ("8/8/4k3/8/2p5/8/B2P2K1/8 w - - 0 1", &[13, 102, 1266, 10276, 135655, 1015133]),
+        // en passant that captures a checking pawn
+        ("8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1", &[15, 126, 1928, 13931, 206379, 1440467]),
+        // castling that gives check
+        ("5k2/8/8/8/8/8/8/4K2R w K - 0 1", &[15, 66, 1198, 6399, 120330, 661072]),
+        ("3k4/8/8/8/8/8/8/R3K3 w Q - 0 1", &[16, 71, 1286, 7418, 141077, 803711]),
+        // castling rights and castling prevented
+        ("r3k2r/1b4bq/8/8/8/8/7B/R3K2R w KQkq - 0 1", &[26, 1141, 27826, 1274206]),
+        ("r3k2r/8/3Q4/8/8/5q2/8/R3K2R b KQkq - 0 1", &[44, 1494, 50509, 1720476]),
+        // promoting out of check, and promoting or underpromoting to give check
+        ("2K2r2/4P3/8/8/8/8/8/3k4 w - - 0 1", &[11, 133, 1442, 19174, 266199, 3821001]),
+        ("4k3/1P6/8/8/8/8/K7/8 w - - 0 1", &[9, 40, 472, 2661, 38983, 217342]),
+        ("8/P1k5/K7/8/8/8/8/8 w - - 0 1", &[6, 27, 273, 1329, 18135, 92683]),
+        // discovered check
+        ("8/8/1P2K3/8/2n5/1q6/8/5k2 b - - 0 1", &[29, 165, 5160, 31961, 1004658]),
+        // self stalemate, then stalemate and checkmate
+        ("K1k5/8/P7/8/8/8/8/8 w - - 0 1", &[2, 6, 13, 63, 382, 2217]),
+        ("8/k1P5/8/1K6/8/8/8/8 w - - 0 1", &[10, 25, 268, 926, 10857, 43261, 567584]),
+        // Chess960
+        ("bqnb1rkr/pp3ppp/3ppn2/2p5/5P2/P2P4/NPP1P1PP/BQ1BNRKR w HFhf - 2 9", &[21, 528, 12189, 326672]),
+        ("2nnrbkr/p1qppppp/8/1ppb4/6PP/3PP3/PPP2P2/BQNNRBKR w HEhe - 1 9", &[21, 807, 18002, 667366]),
+        ("b1q1rrkb/pppppppp/3nn3/8/P7/1PPP4/4PPPP/BQNNRKRB w GE - 1 9", &[20, 479, 10471, 273318]),
+        ("1rqbkrbn/1ppppp1p/1n6/p1N3p1/8/2P4P/PP1PPPP1/1RQBKRBN w FBfb - 0 9", &[29, 502, 14569, 287739]),
+    ];
+
+    #[test]
+    fn perft_suite() {
+        for case in CASES {
+            assert_perft(*case, QUICK_NODES);
+        }
+    }
+
+    #[test]
+    #[ignore]
+    fn perft_suite_deep() {
+        for case in CASES {
+            assert_perft(*case, u64::MAX);
+        }
+    }
+
+    #[test]
+    fn divide_adds_up_to_perft() {
+        // kiwipete
+        let (fen, expected_counts) = CASES[1];
+        let mut game_position = position_from_fen(fen).unwrap();
+        let counts = divide(&mut game_position, 2);
+        assert_eq!(counts.len() as u64, expected_counts[0]);
+        assert_eq!(counts.iter().map(|(_, nodes)| nodes).sum::<u64>(), expected_counts[1]);
+    }
+}
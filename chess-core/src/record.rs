@@ -0,0 +1,89 @@
+use std::fmt;
+use crate::board::*;
+use crate::rules::*;
+use crate::notation::*;
+
+// a whole game: where it started, every move played and how it ended
+#[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GameRecord {
+    pub start_fen: String,
+    pub chess960: bool, // X-FEN can't always tell a Chess960 start position from a classical one
+    pub moves: Vec<Move>,
+    pub outcome: GameOutcome,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GameRecordError {
+    InvalidStartPosition(FenError),
+    IllegalMove(usize), // the index of the first move that isn't legal where it was played
+}
+
+impl fmt::Display for GameRecordError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GameRecordError::InvalidStartPosition(error) => write!(f, "invalid start position: {}", error),
+            GameRecordError::IllegalMove(index) => write!(f, "move {} is illegal", index + 1),
+        }
+    }
+}
+
+impl std::error::Error for GameRecordError {}
+
+// the outcome is passed in since resignations and timeouts can't be worked out from the position
+pub fn get_game_record(game_position: &Position, outcome: GameOutcome) -> GameRecord {
+    let mut start_position = game_position.clone();
+    while !start_position.undo_history.is_empty() {
+        undo_move(&mut start_position);
+    }
+    return GameRecord {
+        start_fen: position_to_fen(&start_position),
+        chess960: game_position.chess960,
+        moves: game_position.undo_history.iter().map(|record| record.m).collect(),
+        outcome,
+    };
+}
+
+// replays the moves from the start position, so they can all be undone again
+pub fn position_from_game_record(record: &GameRecord) -> Result<Position, GameRecordError> {
+    let start_position = if record.chess960 { position_from_chess960_fen(&record.start_fen) } else { position_from_fen(&record.start_fen) };
+    let mut game_position = start_position.map_err(GameRecordError::InvalidStartPosition)?;
+    for (index, m) in record.moves.iter().enumerate() {
+        if !get_all_legal_moves(&mut game_position).contains(m) {
+            return Err(GameRecordError::IllegalMove(index));
+        }
+        play_move(&mut game_position, *m);
+    }
+    return Ok(game_position);
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+
+    fn new_game_record(outcome: GameOutcome) -> GameRecord {
+        let mut game_position = position_from_fen(STARTING_FEN).unwrap();
+        for uci in ["f2f3", "e7e5", "g2g4", "d8h4"] {
+            let m = move_from_uci(&mut game_position, uci).unwrap();
+            play_move(&mut game_position, m);
+        }
+        return get_game_record(&game_position, outcome);
+    }
+
+    #[test]
+    fn game_record_json_round_trip() {
+        let record = new_game_record(GameOutcome::Checkmate { winner: Colours::BLACK });
+        let json = serde_json::to_string(&record).unwrap();
+        assert!(serde_json::from_str::<GameRecord>(&json).unwrap() == record, "{}", json);
+    }
+
+    #[test]
+    fn game_record_bincode_round_trip() {
+        for outcome in [GameOutcome::Ongoing, GameOutcome::Resignation { winner: Colours::WHITE }, GameOutcome::Stalemate] {
+            let record = new_game_record(outcome);
+            let back: GameRecord = bincode::deserialize(&bincode::serialize(&record).unwrap()).unwrap();
+            assert!(back == record);
+            assert_eq!(position_to_fen(&position_from_game_record(&back).unwrap()), "rnb1kbnr/pppp1ppp/8/4p3/6Pq/5P2/PPPPP2P/RNBQKBNR w KQkq - 1 3");
+        }
+    }
+}
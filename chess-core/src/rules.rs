@@ -0,0 +1,1243 @@
+use crate::board::*;
+use crate::bitboard::*;
+use crate::square::*;
+use crate::zobrist::*;
+use std::fmt;
+
+// castling rights bits
+pub const WHITE_KINGSIDE: u8 = 1;
+pub const BLACK_KINGSIDE: u8 = 2;
+pub const WHITE_QUEENSIDE: u8 = 4;
+pub const BLACK_QUEENSIDE: u8 = 8;
+pub const ALL_CASTLING_RIGHTS: u8 = 15;
+
+// reasons a position could never come about in a game, found by validate_position
+#[derive(Debug, Copy, Clone, Eq, PartialEq)]
+pub enum PositionProblem {
+    MissingKing(Colours),
+    TooManyKings(Colours),
+    TooManyPawns(Colours),
+    TooManyPieces(Colours),
+    PawnOnBackRank(Square),
+    OpponentInCheck, // the side that just moved left its king attacked
+    InvalidEnPassant, // no pawn that could just have moved two squares
+}
+
+impl fmt::Display for PositionProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let colour_name = |colour: &Colours| if *colour == Colours::WHITE { "white" } else { "black" };
+        match self {
+            PositionProblem::MissingKing(colour) => write!(f, "{} has no king", colour_name(colour)),
+            PositionProblem::TooManyKings(colour) => write!(f, "{} has more than one king", colour_name(colour)),
+            PositionProblem::TooManyPawns(colour) => write!(f, "{} has more than 8 pawns", colour_name(colour)),
+            PositionProblem::TooManyPieces(colour) => write!(f, "{} has more than 16 pieces", colour_name(colour)),
+            PositionProblem::PawnOnBackRank(sq) => write!(f, "pawn on {}", sq),
+            PositionProblem::OpponentInCheck => write!(f, "the side not to move is in check"),
+            PositionProblem::InvalidEnPassant => write!(f, "no pawn can be taken en passant"),
+        }
+    }
+}
+
+#[derive(Copy, Clone, Eq, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(rename_all = "snake_case"))]
+pub enum GameOutcome {
+    Ongoing,
+    Checkmate { winner: Colours },
+    Stalemate,
+    ThreefoldRepetition,
+    FiftyMoveRule, // claimed by a player, never returned by get_game_outcome
+    SeventyFiveMoveRule,
+    InsufficientMaterial,
+    Resignation { winner: Colours }, // decided by the players, never returned by get_game_outcome
+    Timeout { winner: Colours },     // decided by the clock, never returned by get_game_outcome
+}
+
+pub fn create_move(game_position: &Position, start: Square, end: Square) -> Move {
+    return Move {
+        start,
+        end,
+        start_piece: get_piece_on(game_position, start),
+        end_piece: get_piece_on(game_position, end),
+        promotion: BLANK_PIECE,
+    };
+}
+
+pub fn get_promotion_pieces(colour: Colours) -> [Piece; 4] {
+    if colour == Colours::WHITE {
+        return [WHITE_QUEEN_PIECE, WHITE_ROOK_PIECE, WHITE_BISHOP_PIECE, WHITE_KNIGHT_PIECE];
+    }
+    return [BLACK_QUEEN_PIECE, BLACK_ROOK_PIECE, BLACK_BISHOP_PIECE, BLACK_KNIGHT_PIECE];
+}
+
+pub fn is_promotion_move(m: Move) -> bool {
+    let end_rank = get_square_rank(m.end);
+    return m.start_piece.piece_type == PieceTypes::PAWN && (end_rank == RANK_1 || end_rank == RANK_8);
+}
+
+pub fn has_castling_right(game_position: &Position, right: u8) -> bool {
+    return game_position.castling_rights & right != 0;
+}
+
+pub fn get_kingside_right(colour: Colours) -> u8 {
+    if colour == Colours::WHITE {
+        return WHITE_KINGSIDE;
+    }
+    return BLACK_KINGSIDE;
+}
+
+pub fn get_queenside_right(colour: Colours) -> u8 {
+    if colour == Colours::WHITE {
+        return WHITE_QUEENSIDE;
+    }
+    return BLACK_QUEENSIDE;
+}
+
+// index into castling_rook_files
+fn get_castling_right_index(right: u8) -> usize {
+    return right.trailing_zeros() as usize;
+}
+
+pub fn get_castling_rook_file(game_position: &Position, right: u8) -> File {
+    return game_position.castling_rook_files[get_castling_right_index(right)];
+}
+
+// castling is stored as the king capturing its own rook, which is unambiguous in Chess960
+// where the king may start next to, or already on, its destination square
+pub fn is_castling_move(m: Move) -> bool {
+    return m.start_piece.piece_type == PieceTypes::KING && m.end_piece.colour == m.start_piece.colour;
+}
+
+// the files the king and rook end up on, the same as in classical chess
+pub fn get_castling_destination_files(m: Move) -> (File, File) {
+    if get_square_file(m.end) > get_square_file(m.start) {
+        return (FILE_G, FILE_F);
+    }
+    return (FILE_C, FILE_D);
+}
+
+pub fn is_double_pawn_move(m: Move) -> bool {
+    let (start_rank, end_rank) = (get_square_rank(m.start), get_square_rank(m.end));
+    if m.start_piece.piece_name == PieceNames::WhitePawn &&
+       start_rank == RANK_2 && end_rank == RANK_4 {
+        return true;
+    }
+    if m.start_piece.piece_name == PieceNames::BlackPawn &&
+       start_rank == RANK_7 && end_rank == RANK_5 {
+        return true;
+    }
+    return false;
+}
+
+pub fn is_move_en_passant(m: Move) -> bool {
+    let start = m.start_piece;
+    if start.piece_type != PieceTypes::PAWN {
+        return false;
+    }
+    if get_square_file(m.start) == get_square_file(m.end)  {
+        return false;
+    }
+    if m.end_piece != BLANK_PIECE {
+        return false;
+    }
+    let (start_rank, end_rank) = (get_square_rank(m.start), get_square_rank(m.end));
+    if start.colour == Colours::WHITE && (start_rank != RANK_5 || end_rank != RANK_6) {
+        return false;
+    }
+    if start.colour == Colours::BLACK && (start_rank != RANK_4 || end_rank != RANK_3) {
+        return false;
+    }
+    return true;
+}
+
+// where the pawn taken en passant stands, beside the capturing pawn rather than on the end square
+pub fn get_en_passant_captured_square(m: Move) -> Square {
+    return make_square(get_square_file(m.end), get_square_rank(m.start));
+}
+
+pub fn is_capture_move(m: Move) -> bool {
+    return (m.end_piece != BLANK_PIECE && !is_castling_move(m)) || is_move_en_passant(m);
+}
+
+fn move_piece(game_position: &mut Position, m: Move) {
+    let start = get_piece_on(game_position, m.start);
+    let empty = super::board::Piece { piece_name: PieceNames::Blank, piece_type: PieceTypes::BLANK, colour: Colours::EMPTY}; 
+
+    /////////////////////////////////////////////////////////////////
+
+    // moving a rook or king off its home square, or capturing a rook on its home square,
+    // loses the right to castle with it
+    for (rank, right) in [(RANK_1, WHITE_KINGSIDE), (RANK_8, BLACK_KINGSIDE), (RANK_1, WHITE_QUEENSIDE), (RANK_8, BLACK_QUEENSIDE)] {
+        let rook_sq = make_square(get_castling_rook_file(game_position, right), rank);
+        if m.start == rook_sq || m.end == rook_sq {
+            game_position.castling_rights &= !right;
+        }
+    }
+    if start.piece_type == PieceTypes::KING {
+        game_position.castling_rights &= !(get_kingside_right(start.colour) | get_queenside_right(start.colour));
+    }
+
+    // if a pawn moves 2 squares, it has the possibility of being captured en passant
+    if is_double_pawn_move(m) {
+        game_position.en_passant = Some(get_square_file(m.start));
+    } else {
+        game_position.en_passant = None;
+    }
+
+    /////////////////////////////////////////////////////////////////
+
+
+    // promotion
+    if is_promotion_move(m) {
+        set_piece(game_position, m.end, m.promotion);
+        set_piece(game_position, m.start, empty);
+        return;
+    }
+
+    // castling, the king and rook are both taken off first as they may swap squares
+    if is_castling_move(m) {
+        let (king_file, rook_file) = get_castling_destination_files(m);
+        let rank = get_square_rank(m.end);
+        set_piece(game_position, m.start, empty);
+        set_piece(game_position, m.end, empty);
+        set_piece(game_position, make_square(king_file, rank), start);
+        set_piece(game_position, make_square(rook_file, rank), m.end_piece);
+        return;
+    }
+
+    // regular moves
+    set_piece(game_position, m.end, start); // piece at end becomes piece at start
+    set_piece(game_position, m.start, empty); // piece at start becomes empty
+
+
+    // en passant
+    if is_move_en_passant(m) {
+        set_piece(game_position, get_en_passant_captured_square(m), empty); 
+    }
+}
+
+// how many times the current position has occurred, including now
+pub fn get_repetition_count(game_position: &Position) -> usize {
+    let key = game_position.zobrist_key;
+    let previous = game_position.undo_history.iter().filter(|record| record.zobrist_key == key).count();
+    return previous + 1;
+}
+
+pub fn is_threefold_repetition(game_position: &Position) -> bool {
+    return get_repetition_count(game_position) >= 3;
+}
+
+// neither side can ever checkmate: K v K, K+minor v K, or only bishops all on one square colour
+pub fn is_insufficient_material(game_position: &Position) -> bool {
+    let mut knights = 0;
+    let mut bishops = 0;
+    let mut bishop_square_colours = [false, false];
+    for sq in all_squares() {
+        match get_piece_on(game_position, sq).piece_type {
+            PieceTypes::PAWN | PieceTypes::ROOK | PieceTypes::QUEEN => {
+                return false;
+            }
+            PieceTypes::KNIGHT => {
+                knights += 1;
+            }
+            PieceTypes::BISHOP => {
+                bishops += 1;
+                let (x, y) = get_square_coordinates(sq);
+                bishop_square_colours[(x + y) % 2] = true;
+            }
+            PieceTypes::KING | PieceTypes::BLANK => {
+                //pass
+            }
+        }
+    }
+    if knights + bishops <= 1 {
+        return true;
+    }
+    return knights == 0 && !(bishop_square_colours[0] && bishop_square_colours[1]);
+}
+
+// a draw can be claimed after 50 moves by each side without a capture or pawn move, the game
+// only ends if a player does so
+pub fn can_claim_fifty_move_draw(game_position: &Position) -> bool {
+    return game_position.halfmove_clock >= 100;
+}
+
+// after 75 moves the game is drawn automatically
+pub fn is_seventy_five_move_draw(game_position: &Position) -> bool {
+    return game_position.halfmove_clock >= 150;
+}
+
+pub fn play_move(game_position: &mut Position, m: Move) {
+    let captured_piece = if is_move_en_passant(m) {
+        get_piece_on(game_position, get_en_passant_captured_square(m))
+    } else if is_castling_move(m) {
+        BLANK_PIECE
+    } else {
+        m.end_piece
+    };
+    let record = UndoRecord {
+        m,
+        captured_piece,
+        en_passant: game_position.en_passant,
+        castling_rights: game_position.castling_rights,
+        halfmove_clock: game_position.halfmove_clock,
+        fullmove_number: game_position.fullmove_number,
+        zobrist_key: game_position.zobrist_key,
+    };
+    game_position.undo_history.push(record);
+
+    if m.start_piece.piece_type == PieceTypes::PAWN || captured_piece != BLANK_PIECE {
+        game_position.halfmove_clock = 0;
+    } else {
+        game_position.halfmove_clock += 1;
+    }
+    if game_position.turn == -1 {
+        game_position.fullmove_number += 1;
+    }
+
+    // set_piece keeps the piece keys up to date, the rest of the key is swapped out around the move
+    game_position.zobrist_key ^= get_castling_key(game_position.castling_rights) ^ get_en_passant_key(game_position) ^ get_turn_key(game_position.turn);
+    move_piece(game_position, m);
+    game_position.turn *= -1;
+    game_position.zobrist_key ^= get_castling_key(game_position.castling_rights) ^ get_en_passant_key(game_position) ^ get_turn_key(game_position.turn);
+    debug_assert_eq!(game_position.zobrist_key, compute_zobrist_key(game_position));
+}
+
+
+
+// the moves a generation stage produces. captures include en passant and every promotion, so
+// a search can try them first, quiets are everything else, castling included
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub enum MoveStage {
+    CAPTURES,
+    QUIETS,
+}
+
+// one move from sq to every square in targets
+fn push_moves_to_targets(move_list: &mut MoveList, sq: Square, mut targets: Bitboard) {
+    while targets != 0 {
+        let target = pop_lsb(&mut targets);
+        push_move(move_list, get_compact_move(sq, target, PieceTypes::BLANK));
+    }
+}
+
+// a pawn reaching the last rank is expanded into one move per promotion piece
+fn push_pawn_moves_to_targets(move_list: &mut MoveList, sq: Square, mut targets: Bitboard) {
+    while targets != 0 {
+        let target = pop_lsb(&mut targets);
+        let target_rank = get_square_rank(target);
+        if target_rank != RANK_1 && target_rank != RANK_8 {
+            push_move(move_list, get_compact_move(sq, target, PieceTypes::BLANK));
+            continue;
+        }
+        for promotion_type in [PieceTypes::QUEEN, PieceTypes::ROOK, PieceTypes::BISHOP, PieceTypes::KNIGHT] {
+            push_move(move_list, get_compact_move(sq, target, promotion_type));
+        }
+    }
+}
+
+fn add_possible_pawn_moves(game_position: &Position, stage: MoveStage, sq: Square, move_list: &mut MoveList) {
+    let col = get_piece_on(game_position, sq).colour;
+    let c = colour_index(col);
+    let occupied = game_position.bitboards.occupied;
+    let last_rank: Bitboard = 0xff << 56 | 0xff;
+    let (start_rank, en_passant_rank) = if col == Colours::WHITE { (RANK_2, RANK_5) } else { (RANK_7, RANK_4) };
+
+    // forward 1 square, or 2 from the starting rank. only promotions count as captures. pawns
+    // are never on the last rank, so there is always a square in front
+    let one_forward = offset_square(sq, 0, col as i32).unwrap();
+    let mut pushes = 0;
+    if occupied & square_bit(one_forward) == 0 {
+        pushes |= square_bit(one_forward);
+        if get_square_rank(sq) == start_rank {
+            let two_forward = offset_square(one_forward, 0, col as i32).unwrap();
+            if occupied & square_bit(two_forward) == 0 {
+                pushes |= square_bit(two_forward);
+            }
+        }
+    }
+    if stage == MoveStage::QUIETS {
+        push_pawn_moves_to_targets(move_list, sq, pushes & !last_rank);
+        return;
+    }
+
+    let mut targets = (pushes & last_rank) | (get_pawn_attacks(c, sq) & game_position.bitboards.colours[1 - c]);
+
+    // en passant
+    if let Some(file) = game_position.en_passant && get_square_rank(sq) == en_passant_rank {
+        targets |= get_pawn_attacks(c, sq) & square_bit(make_square(file, get_square_rank(one_forward)));
+    }
+    push_pawn_moves_to_targets(move_list, sq, targets);
+}
+
+// castling: the right must still be held and the king and rook must be on their home squares.
+// every square either of them crosses or lands on must be empty apart from the two of them,
+// and the king may not start in, pass through or land on an attacked square (landing is
+// checked with the other legal move tests, once the rook has moved)
+fn add_possible_castling_moves(game_position: &Position, sq: Square, move_list: &mut MoveList) {
+    let col = get_piece_on(game_position, sq).colour;
+    let home_rank = if col == Colours::WHITE { RANK_1 } else { RANK_8 };
+    let king_file = get_square_file(sq);
+    if get_square_rank(sq) != home_rank || (!game_position.chess960 && king_file != FILE_E) {
+        return;
+    }
+    let rook = if col == Colours::WHITE { WHITE_ROOK_PIECE } else { BLACK_ROOK_PIECE };
+    let enemy = get_opposite_colour(col);
+    for right in [get_kingside_right(col), get_queenside_right(col)] {
+        if !has_castling_right(game_position, right) {
+            continue;
+        }
+        let rook_file = get_castling_rook_file(game_position, right);
+        let rook_sq = make_square(rook_file, home_rank);
+        if get_piece_on(game_position, rook_sq) != rook || (rook_file > king_file) != (right == get_kingside_right(col)) {
+            continue;
+        }
+        let m = create_move(game_position, sq, rook_sq);
+        let (king_end_file, rook_end_file) = get_castling_destination_files(m);
+        let king_path = get_squares_between(sq, make_square(king_end_file, home_rank)) | square_bit(make_square(king_end_file, home_rank));
+        let rook_path = get_squares_between(rook_sq, make_square(rook_end_file, home_rank)) | square_bit(make_square(rook_end_file, home_rank));
+        if (king_path | rook_path) & game_position.bitboards.occupied & !square_bit(sq) & !square_bit(rook_sq) != 0 {
+            continue;
+        }
+        let mut king_squares = king_path | square_bit(sq);
+        let mut attacked = false;
+        while king_squares != 0 && !attacked {
+            attacked = is_square_attacked(game_position, pop_lsb(&mut king_squares), enemy);
+        }
+        if attacked {
+            continue;
+        }
+        push_move(move_list, pack_move(m));
+    }
+}
+
+// moves for the side to move that may leave its own king in check
+pub fn generate_possible_moves(game_position: &Position, stage: MoveStage, move_list: &mut MoveList) {
+    let c = get_turn_index(game_position);
+    let occupied = game_position.bitboards.occupied;
+    let stage_targets = match stage {
+        MoveStage::CAPTURES => game_position.bitboards.colours[1 - c],
+        MoveStage::QUIETS => !occupied,
+    };
+    let mut pieces = game_position.bitboards.colours[c];
+    while pieces != 0 {
+        let sq = pop_lsb(&mut pieces);
+        let attacks = match get_piece_on(game_position, sq).piece_type {
+            PieceTypes::KNIGHT => get_knight_attacks(sq),
+            PieceTypes::BISHOP => get_bishop_attacks(sq, occupied),
+            PieceTypes::ROOK => get_rook_attacks(sq, occupied),
+            PieceTypes::QUEEN => get_queen_attacks(sq, occupied),
+            PieceTypes::KING => {
+                if stage == MoveStage::QUIETS {
+                    add_possible_castling_moves(game_position, sq, move_list);
+                }
+                get_king_attacks(sq)
+            }
+            PieceTypes::PAWN => {
+                add_possible_pawn_moves(game_position, stage, sq, move_list);
+                continue;
+            }
+            PieceTypes::BLANK => unreachable!(),
+        };
+        push_moves_to_targets(move_list, sq, attacks & stage_targets);
+    }
+}
+
+// colour index of the side to move
+fn get_turn_index(game_position: &Position) -> usize {
+    if game_position.turn == 1 {
+        return 0;
+    }
+    return 1;
+}
+
+pub fn get_turn_colour(game_position: &Position) -> Colours {
+    if game_position.turn == 1 {
+        return Colours::WHITE;
+    }
+    return Colours::BLACK;
+}
+
+pub fn get_opposite_colour(colour: Colours) -> Colours {
+    return match colour {
+        Colours::WHITE => Colours::BLACK,
+        Colours::BLACK => Colours::WHITE,
+        Colours::EMPTY => Colours::EMPTY,
+    };
+}
+
+// the pieces of colour by attacking the square. looks outward from the square with each piece
+// type's moves, e.g. a knight on the square would attack exactly the squares a knight could
+// attack it from
+pub fn get_square_attackers(game_position: &Position, sq: Square, by: Colours) -> Bitboard {
+    return get_square_attackers_through(game_position, sq, by, game_position.bitboards.occupied);
+}
+
+// as get_square_attackers, with sliders blocked only by the pieces in occupied
+pub fn get_square_attackers_through(game_position: &Position, sq: Square, by: Colours, occupied: Bitboard) -> Bitboard {
+    let c = colour_index(by);
+    let pieces = &game_position.bitboards.pieces[c];
+
+    let pawns = pieces[piece_type_index(PieceTypes::PAWN)];
+    let knights = pieces[piece_type_index(PieceTypes::KNIGHT)];
+    let kings = pieces[piece_type_index(PieceTypes::KING)];
+    let queens = pieces[piece_type_index(PieceTypes::QUEEN)];
+    let diagonal_sliders = pieces[piece_type_index(PieceTypes::BISHOP)] | queens;
+    let straight_sliders = pieces[piece_type_index(PieceTypes::ROOK)] | queens;
+
+    // pawns attacking the square sit where a pawn of the other colour would attack
+    return (get_pawn_attacks(1 - c, sq) & pawns) |
+        (get_knight_attacks(sq) & knights) |
+        (get_king_attacks(sq) & kings) |
+        (get_bishop_attacks(sq, occupied) & diagonal_sliders) |
+        (get_rook_attacks(sq, occupied) & straight_sliders);
+}
+
+pub fn is_square_attacked(game_position: &Position, sq: Square, by: Colours) -> bool {
+    return get_square_attackers(game_position, sq, by) != 0;
+}
+
+// None if the colour has no king, which FEN import rejects but empty_position and set_piece can
+// leave. a side without a king is never in check and has no pinned pieces
+pub fn get_king_square(game_position: &Position, colour: Colours) -> Option<Square> {
+    let king = game_position.bitboards.pieces[colour_index(colour)][piece_type_index(PieceTypes::KING)];
+    if king == 0 {
+        return None;
+    }
+    return Some(get_lsb(king));
+}
+
+// the pieces giving check to the side to move
+pub fn get_checkers(game_position: &Position) -> Bitboard {
+    let colour = get_turn_colour(game_position);
+    return match get_king_square(game_position, colour) {
+        Some(sq) => get_square_attackers(game_position, sq, get_opposite_colour(colour)),
+        None => 0,
+    };
+}
+
+// every square the side attacks, whether empty or occupied by either side
+pub fn get_attack_map(game_position: &Position, colour: Colours) -> Bitboard {
+    let c = colour_index(colour);
+    let occupied = game_position.bitboards.occupied;
+    let mut attacks = 0;
+    let mut pieces = game_position.bitboards.colours[c];
+    while pieces != 0 {
+        let sq = pop_lsb(&mut pieces);
+        attacks |= match get_piece_on(game_position, sq).piece_type {
+            PieceTypes::PAWN => get_pawn_attacks(c, sq),
+            PieceTypes::KNIGHT => get_knight_attacks(sq),
+            PieceTypes::BISHOP => get_bishop_attacks(sq, occupied),
+            PieceTypes::ROOK => get_rook_attacks(sq, occupied),
+            PieceTypes::QUEEN => get_queen_attacks(sq, occupied),
+            PieceTypes::KING => get_king_attacks(sq),
+            PieceTypes::BLANK => unreachable!(),
+        };
+    }
+    return attacks;
+}
+
+// a piece that can't leave the line between its king and the enemy slider behind it
+#[derive(Copy, Clone, Eq, PartialEq)]
+pub struct Pin {
+    pub sq: Square,
+    pub ray: Bitboard, // the squares it may still move to, up to and including the pinning piece
+}
+
+// enemy sliders that would attack the king if none of its own pieces were in the way
+fn get_pinning_candidates(game_position: &Position, colour: Colours, king_sq: Square) -> Bitboard {
+    let enemy = &game_position.bitboards.pieces[1 - colour_index(colour)];
+    let enemy_occupied = game_position.bitboards.colours[1 - colour_index(colour)];
+    let queens = enemy[piece_type_index(PieceTypes::QUEEN)];
+    let diagonal_sliders = enemy[piece_type_index(PieceTypes::BISHOP)] | queens;
+    let straight_sliders = enemy[piece_type_index(PieceTypes::ROOK)] | queens;
+    return (get_bishop_attacks(king_sq, enemy_occupied) & diagonal_sliders) |
+        (get_rook_attacks(king_sq, enemy_occupied) & straight_sliders);
+}
+
+// the pieces of the colour pinned to their king
+pub fn get_pins(game_position: &Position, colour: Colours) -> Vec<Pin> {
+    let king_sq = match get_king_square(game_position, colour) {
+        Some(sq) => sq,
+        None => return vec![],
+    };
+    let mut candidates = get_pinning_candidates(game_position, colour, king_sq);
+    let mut pins = vec![];
+    while candidates != 0 {
+        let pinner = pop_lsb(&mut candidates);
+        let between = get_squares_between(king_sq, pinner);
+        let blockers = between & game_position.bitboards.occupied;
+        if blockers.count_ones() == 1 {
+            pins.push(Pin { sq: get_lsb(blockers), ray: between | square_bit(pinner) });
+        }
+    }
+    return pins;
+}
+
+// the squares of the pieces in get_pins, without allocating
+pub fn get_pinned_pieces(game_position: &Position, colour: Colours) -> Bitboard {
+    let king_sq = match get_king_square(game_position, colour) {
+        Some(sq) => sq,
+        None => return 0,
+    };
+    let mut candidates = get_pinning_candidates(game_position, colour, king_sq);
+    let mut pinned = 0;
+    while candidates != 0 {
+        let blockers = get_squares_between(king_sq, pop_lsb(&mut candidates)) & game_position.bitboards.occupied;
+        if blockers.count_ones() == 1 {
+            pinned |= blockers;
+        }
+    }
+    return pinned;
+}
+
+// whether the side that just moved left its king attacked
+pub fn is_check(game_position: &Position) -> bool {
+    let colour = get_turn_colour(game_position);
+    return match get_king_square(game_position, get_opposite_colour(colour)) {
+        Some(sq) => is_square_attacked(game_position, sq, colour),
+        None => false,
+    };
+}
+
+// unlike is_check, this looks at the king of the side to move
+pub fn is_in_check(game_position: &Position) -> bool {
+    let colour = get_turn_colour(game_position);
+    return match get_king_square(game_position, colour) {
+        Some(sq) => is_square_attacked(game_position, sq, get_opposite_colour(colour)),
+        None => false,
+    };
+}
+
+pub fn undo_move(game_position: &mut Position) {
+    let record = match game_position.undo_history.pop() {
+        Some(record) => record,
+        None => return,
+    };
+    let m = record.m;
+
+    game_position.turn *= -1;
+    game_position.en_passant = record.en_passant;
+    game_position.castling_rights = record.castling_rights;
+    game_position.halfmove_clock = record.halfmove_clock;
+    game_position.fullmove_number = record.fullmove_number;
+
+    if is_castling_move(m) {
+        let (king_file, rook_file) = get_castling_destination_files(m);
+        let rank = get_square_rank(m.end);
+        set_piece(game_position, make_square(king_file, rank), BLANK_PIECE);
+        set_piece(game_position, make_square(rook_file, rank), BLANK_PIECE);
+        set_piece(game_position, m.start, m.start_piece);
+        set_piece(game_position, m.end, m.end_piece);
+    } else {
+        set_piece(game_position, m.end, BLANK_PIECE);
+        set_piece(game_position, m.start, m.start_piece);
+        if is_move_en_passant(m) {
+            set_piece(game_position, get_en_passant_captured_square(m), record.captured_piece);
+        } else {
+            set_piece(game_position, m.end, record.captured_piece);
+        }
+    }
+
+    // set_piece has put the piece keys back, the castling, en passant and turn keys come from the record
+    game_position.zobrist_key = record.zobrist_key;
+    debug_assert_eq!(game_position.zobrist_key, compute_zobrist_key(game_position));
+}
+
+// what is_legal_move needs to know about the side to move, worked out once per position
+struct LegalityInfo {
+    king_sq: Option<Square>,
+    checkers: Bitboard,
+    pinned: Bitboard,
+}
+
+fn get_legality_info(game_position: &Position) -> LegalityInfo {
+    let colour = get_turn_colour(game_position);
+    return LegalityInfo {
+        king_sq: get_king_square(game_position, colour),
+        checkers: get_checkers(game_position),
+        pinned: get_pinned_pieces(game_position, colour),
+    };
+}
+
+// whether a possible move leaves the mover's king safe. en passant and castling, which move
+// two pieces, are played out, everything else follows from the checkers and pins
+fn is_legal_move(game_position: &mut Position, info: &LegalityInfo, cm: CompactMove) -> bool {
+    let m = unpack_move(game_position, cm);
+    if is_move_en_passant(m) || is_castling_move(m) {
+        play_move(game_position, m);
+        let legal = !is_check(game_position);
+        undo_move(game_position);
+        return legal;
+    }
+    let (start_sq, end_sq) = (get_compact_move_start(cm), get_compact_move_end(cm));
+    // with no king to leave in check, every move is legal
+    let king_sq = match info.king_sq {
+        Some(sq) => sq,
+        None => return true,
+    };
+
+    // the king can't hide from a slider along the line it is attacked on
+    if start_sq == king_sq {
+        let occupied = game_position.bitboards.occupied & !square_bit(start_sq);
+        let enemy = get_opposite_colour(m.start_piece.colour);
+        return get_square_attackers_through(game_position, end_sq, enemy, occupied) == 0;
+    }
+
+    // anything else has to take a lone checking piece or block it
+    if info.checkers != 0 {
+        if info.checkers.count_ones() > 1 {
+            return false;
+        }
+        let checker_sq = get_lsb(info.checkers);
+        if (info.checkers | get_squares_between(king_sq, checker_sq)) & square_bit(end_sq) == 0 {
+            return false;
+        }
+    }
+
+    // and a pinned piece can only move along the pin
+    return info.pinned & square_bit(start_sq) == 0 || get_line_through(king_sq, start_sq) & square_bit(end_sq) != 0;
+}
+
+// appends the stage's legal moves to move_list, without allocating
+pub fn generate_legal_moves(game_position: &mut Position, stage: MoveStage, move_list: &mut MoveList) {
+    let mut possible_moves = new_move_list();
+    generate_possible_moves(game_position, stage, &mut possible_moves);
+    let info = get_legality_info(game_position);
+    for cm in get_moves(&possible_moves) {
+        if is_legal_move(game_position, &info, *cm) {
+            push_move(move_list, *cm);
+        }
+    }
+}
+
+// every legal move, captures first
+pub fn generate_all_legal_moves(game_position: &mut Position, move_list: &mut MoveList) {
+    generate_legal_moves(game_position, MoveStage::CAPTURES, move_list);
+    generate_legal_moves(game_position, MoveStage::QUIETS, move_list);
+}
+
+// legal captures and promotions, all a quiescence search needs to look at
+pub fn generate_captures(game_position: &mut Position, move_list: &mut MoveList) {
+    generate_legal_moves(game_position, MoveStage::CAPTURES, move_list);
+}
+
+// legal moves that give check without capturing or promoting
+pub fn generate_quiet_checks(game_position: &mut Position, move_list: &mut MoveList) {
+    let mut possible_moves = new_move_list();
+    generate_possible_moves(game_position, MoveStage::QUIETS, &mut possible_moves);
+    let info = get_legality_info(game_position);
+    for cm in get_moves(&possible_moves) {
+        if !is_legal_move(game_position, &info, *cm) {
+            continue;
+        }
+        play_move(game_position, unpack_move(game_position, *cm));
+        let gives_check = is_in_check(game_position);
+        undo_move(game_position);
+        if gives_check {
+            push_move(move_list, *cm);
+        }
+    }
+}
+
+// every legal move when the side to move is in check, and nothing otherwise. only the king may
+// move out of a double check, and from a single check any other piece has to take the checking
+// piece or land between it and the king
+pub fn generate_evasions(game_position: &mut Position, move_list: &mut MoveList) {
+    let info = get_legality_info(game_position);
+    let king_sq = match info.king_sq {
+        Some(sq) if info.checkers != 0 => sq,
+        _ => return,
+    };
+    let mut evasion_squares = 0;
+    if info.checkers.count_ones() == 1 {
+        evasion_squares = info.checkers | get_squares_between(king_sq, get_lsb(info.checkers));
+    }
+
+    for stage in [MoveStage::CAPTURES, MoveStage::QUIETS] {
+        let mut possible_moves = new_move_list();
+        generate_possible_moves(game_position, stage, &mut possible_moves);
+        for cm in get_moves(&possible_moves) {
+            let m = unpack_move(game_position, *cm);
+            // the pawn taken en passant is not on the end square, so leave it to the legality test
+            if m.start_piece.piece_type != PieceTypes::KING && evasion_squares & square_bit(m.end) == 0 && !is_move_en_passant(m) {
+                continue;
+            }
+            if is_legal_move(game_position, &info, *cm) {
+                push_move(move_list, *cm);
+            }
+        }
+    }
+}
+
+pub fn get_piece_legal_moves(game_position: &mut Position, sq: Square) -> Vec<Move> {
+    let mut legal_moves = get_all_legal_moves(game_position);
+    legal_moves.retain(|m| m.start == sq);
+    return legal_moves;
+}
+
+pub fn get_all_legal_moves(game_position: &mut Position) -> Vec<Move> {
+    let mut move_list = new_move_list();
+    generate_all_legal_moves(game_position, &mut move_list);
+    return get_moves(&move_list).iter().map(|cm| unpack_move(game_position, *cm)).collect();
+}
+
+pub fn get_game_outcome(game_position: &mut Position) -> GameOutcome {
+    let mut move_list = new_move_list();
+    generate_all_legal_moves(game_position, &mut move_list);
+    return get_game_outcome_with_legal_moves(game_position, move_list.len > 0);
+}
+
+// for callers that already know whether the side to move has a legal move
+pub fn get_game_outcome_with_legal_moves(game_position: &mut Position, has_legal_moves: bool) -> GameOutcome {
+    if !has_legal_moves {
+        if is_in_check(game_position) {
+            let winner = if game_position.turn == 1 { Colours::BLACK } else { Colours::WHITE };
+            return GameOutcome::Checkmate { winner };
+        }
+        return GameOutcome::Stalemate;
+    }
+    if is_seventy_five_move_draw(game_position) {
+        return GameOutcome::SeventyFiveMoveRule;
+    }
+    if is_insufficient_material(game_position) {
+        return GameOutcome::InsufficientMaterial;
+    }
+    if is_threefold_repetition(game_position) {
+        return GameOutcome::ThreefoldRepetition;
+    }
+    return GameOutcome::Ongoing;
+}
+
+// everything wrong with the position, empty if it is safe to generate moves from. castling
+// rights without their king or rook are left alone, as they are never used
+pub fn validate_position(game_position: &Position) -> Vec<PositionProblem> {
+    let mut problems = vec![];
+    for colour in [Colours::WHITE, Colours::BLACK] {
+        let c = colour_index(colour);
+        let kings = game_position.bitboards.pieces[c][piece_type_index(PieceTypes::KING)].count_ones();
+        if kings == 0 {
+            problems.push(PositionProblem::MissingKing(colour));
+        } else if kings > 1 {
+            problems.push(PositionProblem::TooManyKings(colour));
+        }
+        if game_position.bitboards.pieces[c][piece_type_index(PieceTypes::PAWN)].count_ones() > 8 {
+            problems.push(PositionProblem::TooManyPawns(colour));
+        }
+        if game_position.bitboards.colours[c].count_ones() > 16 {
+            problems.push(PositionProblem::TooManyPieces(colour));
+        }
+    }
+
+    for sq in all_squares() {
+        let rank = get_square_rank(sq);
+        if (rank == RANK_1 || rank == RANK_8) && get_piece_on(game_position, sq).piece_type == PieceTypes::PAWN {
+            problems.push(PositionProblem::PawnOnBackRank(sq));
+        }
+    }
+
+    // the en passant pawn must be just past the square it skipped, with that square and the one
+    // it started on empty
+    if let Some(file) = game_position.en_passant {
+        let (pawn, rank, skipped_rank, start_rank) = if game_position.turn == 1 { (BLACK_PAWN_PIECE, RANK_5, RANK_6, RANK_7) } else { (WHITE_PAWN_PIECE, RANK_4, RANK_3, RANK_2) };
+        let piece_on = |rank: Rank| get_piece_on(game_position, make_square(file, rank));
+        if piece_on(rank) != pawn || piece_on(skipped_rank) != BLANK_PIECE || piece_on(start_rank) != BLANK_PIECE {
+            problems.push(PositionProblem::InvalidEnPassant);
+        }
+    }
+
+    // looking for check needs exactly one king on each side
+    let has_both_kings = !problems.iter().any(|problem| matches!(problem, PositionProblem::MissingKing(_) | PositionProblem::TooManyKings(_)));
+    if has_both_kings && is_check(game_position) {
+        problems.push(PositionProblem::OpponentInCheck);
+    }
+    return problems;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::notation::*;
+    use rand::{Rng, SeedableRng};
+    use rand::rngs::StdRng;
+
+    fn play_uci(game_position: &mut Position, uci: &str) {
+        let m = move_from_uci(game_position, uci).unwrap();
+        play_move(game_position, m);
+    }
+
+    // plays the moves ("undo" takes one back), then checks the fen and the castling moves on offer
+    fn assert_castling(fen: &str, moves: &[&str], expected_fen: &str, expected_castling: &[&str]) {
+        let mut game_position = position_from_fen(fen).unwrap();
+        for uci in moves {
+            if *uci == "undo" {
+                undo_move(&mut game_position);
+            } else {
+                play_uci(&mut game_position, uci);
+            }
+        }
+        assert_eq!(position_to_fen(&game_position), expected_fen);
+        let mut castling: Vec<String> = get_all_legal_moves(&mut game_position).into_iter()
+            .filter(|m| is_castling_move(*m))
+            .map(|m| move_to_uci(&game_position, m))
+            .collect();
+        let mut expected = expected_castling.to_vec();
+        castling.sort();
+        expected.sort();
+        assert_eq!(castling, expected, "{} after {:?}", fen, moves);
+    }
+
+    #[test]
+    fn castling_both_ways() {
+        assert_castling("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", &["e1g1", "e1c1"]);
+    }
+
+    #[test]
+    fn castling_blocked_by_a_piece_between_king_and_rook() {
+        assert_castling("r3k2r/8/8/8/8/8/8/R3KB1R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/8/R3KB1R w KQkq - 0 1", &["e1c1"]);
+        assert_castling("r3k2r/8/8/8/8/8/8/R3K1NR w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/8/R3K1NR w KQkq - 0 1", &["e1c1"]);
+        assert_castling("r3k2r/8/8/8/8/8/8/RN2K2R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/8/RN2K2R w KQkq - 0 1", &["e1g1"]);
+    }
+
+    // b1 may be attacked, only the squares the king crosses matter
+    #[test]
+    fn castling_past_an_attacked_square_the_king_does_not_cross() {
+        assert_castling("r3k2r/8/8/8/8/8/b7/R3K2R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/b7/R3K2R w KQkq - 0 1", &["e1g1", "e1c1"]);
+        assert_castling("1r2k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1", &[], "1r2k2r/8/8/8/8/8/8/R3K2R w KQk - 0 1", &["e1g1", "e1c1"]);
+    }
+
+    #[test]
+    fn no_castling_in_check_or_through_or_into_an_attacked_square() {
+        assert_castling("r3k2r/8/8/8/8/8/4r3/R3K2R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/4r3/R3K2R w KQkq - 0 1", &[]);
+        assert_castling("r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/5r2/R3K2R w KQkq - 0 1", &["e1c1"]);
+        assert_castling("r3k2r/8/8/8/8/8/6r1/R3K2R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/6r1/R3K2R w KQkq - 0 1", &["e1c1"]);
+        assert_castling("r3k2r/8/8/8/8/8/3r4/R3K2R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/3r4/R3K2R w KQkq - 0 1", &["e1g1"]);
+        assert_castling("r3k2r/8/8/8/8/8/2r5/R3K2R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/2r5/R3K2R w KQkq - 0 1", &["e1g1"]);
+    }
+
+    // the right alone is not enough, the king and rook must still be there
+    #[test]
+    fn castling_needs_the_king_and_rook_on_their_squares() {
+        assert_castling("r3k2r/8/8/8/8/8/8/4K2n w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/8/4K2n w KQkq - 0 1", &[]);
+        assert_castling("r3k2r/8/8/8/8/8/4K3/R6R w KQkq - 0 1", &[], "r3k2r/8/8/8/8/8/4K3/R6R w KQkq - 0 1", &[]);
+    }
+
+    #[test]
+    fn capturing_a_rook_on_its_home_square_removes_that_right() {
+        assert_castling("r3k2r/8/8/8/8/8/6b1/R3K2R b KQkq - 0 1", &["g2h1"], "r3k2r/8/8/8/8/8/8/R3K2b w Qkq - 0 2", &["e1c1"]);
+        assert_castling("r3k1nr/8/8/8/8/8/8/R5KR w kq - 0 1", &["h1h8"], "r3k1nR/8/8/8/8/8/8/R5K1 b q - 0 1", &["e8c8"]);
+    }
+
+    #[test]
+    fn undoing_a_king_move_restores_the_rights() {
+        assert_castling("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", &["e1f1", "undo"], "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", &["e1g1", "e1c1"]);
+    }
+
+    #[test]
+    fn castling_moves_the_rook_and_uses_up_both_rights() {
+        assert_castling("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1", &["e1g1"], "r3k2r/8/8/8/8/8/8/R4RK1 b kq - 1 1", &["e8c8"]);
+    }
+
+    // the king may already be on its castling square and the rook land on an attacked square
+    #[test]
+    fn chess960_castling() {
+        assert_castling("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", &[], "1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", &["g1h1", "g1b1"]);
+        assert_castling("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1", &["g1h1"], "1r4kr/8/8/8/8/8/8/1R3RK1 b hb - 1 1", &["g8h8"]);
+    }
+
+    #[test]
+    fn the_castling_rook_cannot_shield_the_king_from_the_square_it_leaves() {
+        assert_castling("4k3/8/8/8/8/8/8/qRK5 w B - 0 1", &[], "4k3/8/8/8/8/8/8/qRK5 w B - 0 1", &[]);
+    }
+
+    #[test]
+    fn threefold_repetition() {
+        let mut game_position = position_from_fen(STARTING_FEN).unwrap();
+        assert_eq!(get_repetition_count(&game_position), 1);
+        for count in [2, 3] {
+            for uci in ["g1f3", "g8f6", "f3g1", "f6g8"] {
+                play_uci(&mut game_position, uci);
+            }
+            assert_eq!(get_repetition_count(&game_position), count);
+        }
+        assert!(is_threefold_repetition(&game_position));
+        assert!(get_game_outcome(&mut game_position) == GameOutcome::ThreefoldRepetition);
+    }
+
+    // the same placement with different castling or en passant rights is a different position
+    #[test]
+    fn repetition_needs_the_same_rights() {
+        let mut game_position = position_from_fen("r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1").unwrap();
+        for uci in ["h1h2", "h8h7", "h2h1", "h7h8"] {
+            play_uci(&mut game_position, uci);
+        }
+        assert_eq!(get_repetition_count(&game_position), 1);
+
+        let mut game_position = position_from_fen("4k3/8/8/8/3p4/8/4P3/4K3 w - - 0 1").unwrap();
+        play_uci(&mut game_position, "e2e4");
+        for uci in ["e8d8", "e1d1", "d8e8", "d1e1"] {
+            play_uci(&mut game_position, uci);
+        }
+        assert_eq!(get_repetition_count(&game_position), 1);
+        for uci in ["e8d8", "e1d1", "d8e8", "d1e1"] {
+            play_uci(&mut game_position, uci);
+        }
+        assert_eq!(get_repetition_count(&game_position), 2);
+    }
+
+    #[test]
+    fn insufficient_material() {
+        let cases = [
+            ("4k3/8/8/8/8/8/8/4K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+            ("4k3/8/8/8/8/8/8/1N2K3 w - - 0 1", true),
+            // both bishops on dark squares, then one on each colour
+            ("4kb2/8/8/8/8/8/8/2B1K3 w - - 0 1", true),
+            ("2b1k3/8/8/8/8/8/8/2B1K3 w - - 0 1", false),
+            ("1n2k3/8/8/8/8/8/8/1N2K3 w - - 0 1", false),
+            ("4k3/8/8/8/8/8/8/1N2KN2 w - - 0 1", false),
+        ];
+        for (fen, expected) in cases {
+            assert_eq!(is_insufficient_material(&position_from_fen(fen).unwrap()), expected, "{}", fen);
+        }
+    }
+
+    #[test]
+    fn checkmate_and_stalemate() {
+        let mut game_position = position_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1").unwrap();
+        play_uci(&mut game_position, "a1a8");
+        assert!(get_game_outcome(&mut game_position) == GameOutcome::Checkmate { winner: Colours::WHITE });
+
+        let mut game_position = position_from_fen("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1").unwrap();
+        assert!(get_game_outcome(&mut game_position) == GameOutcome::Stalemate);
+    }
+
+    // mate on the move that reaches 75 moves still counts
+    #[test]
+    fn checkmate_comes_before_the_seventy_five_move_rule() {
+        let mut game_position = position_from_fen("6k1/5ppp/8/8/8/8/8/R5K1 w - - 149 90").unwrap();
+        play_uci(&mut game_position, "a1a8");
+        assert!(is_seventy_five_move_draw(&game_position));
+        assert!(get_game_outcome(&mut game_position) == GameOutcome::Checkmate { winner: Colours::WHITE });
+    }
+
+    #[test]
+    fn fifty_moves_can_be_claimed_and_seventy_five_end_the_game() {
+        let mut game_position = position_from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 98 80").unwrap();
+        play_uci(&mut game_position, "a1a2");
+        assert!(!can_claim_fifty_move_draw(&game_position));
+        play_uci(&mut game_position, "e8d8");
+        assert!(can_claim_fifty_move_draw(&game_position));
+        assert!(get_game_outcome(&mut game_position) == GameOutcome::Ongoing);
+
+        let mut game_position = position_from_fen("4k3/8/8/8/8/8/8/R3K3 w - - 149 105").unwrap();
+        play_uci(&mut game_position, "a1a2");
+        assert!(get_game_outcome(&mut game_position) == GameOutcome::SeventyFiveMoveRule);
+    }
+
+    // FEN import rejects these, but a position built square by square can be missing a king
+    #[test]
+    fn positions_without_a_king() {
+        let mut game_position = empty_position();
+        set_piece(&mut game_position, make_square(FILE_A, RANK_1), WHITE_ROOK_PIECE);
+        set_piece(&mut game_position, make_square(FILE_E, RANK_8), BLACK_KING_PIECE);
+        assert!(get_king_square(&game_position, Colours::WHITE).is_none());
+        assert!(!is_in_check(&game_position));
+        assert!(!is_check(&game_position));
+        assert!(get_pins(&game_position, Colours::WHITE).is_empty());
+        assert_eq!(get_all_legal_moves(&mut game_position).len(), 14);
+
+        play_uci(&mut game_position, "a1a8");
+        assert!(is_in_check(&game_position));
+        assert_eq!(get_checkers(&game_position), square_bit(make_square(FILE_A, RANK_8)));
+        let mut evasions = new_move_list();
+        generate_evasions(&mut game_position, &mut evasions);
+        assert_eq!(evasions.len, 3);
+        assert!(get_game_outcome(&mut game_position) == GameOutcome::Ongoing);
+    }
+
+    // every problem FEN import reports for a position, none if it is accepted
+    fn get_fen_problems(fen: &str) -> Vec<PositionProblem> {
+        return match position_from_fen(fen) {
+            Ok(_) => vec![],
+            Err(FenError::InvalidPosition(problems)) => problems,
+            Err(e) => panic!("{}: {}", fen, e),
+        };
+    }
+
+    #[test]
+    fn validation_counts_kings() {
+        assert_eq!(get_fen_problems("8/8/8/8/8/8/8/4K3 w - - 0 1"), [PositionProblem::MissingKing(Colours::BLACK)]);
+        assert_eq!(get_fen_problems("4k3/8/8/8/8/8/8/3KK3 w - - 0 1"), [PositionProblem::TooManyKings(Colours::WHITE)]);
+    }
+
+    #[test]
+    fn validation_counts_pawns_and_pieces() {
+        assert_eq!(get_fen_problems("4k3/8/8/8/8/8/PPPPPPPP/P3K3 w - - 0 1"),
+            [PositionProblem::TooManyPawns(Colours::WHITE), PositionProblem::PawnOnBackRank(make_square(FILE_A, RANK_1))]);
+        assert_eq!(get_fen_problems("qqqqkqqq/qqqqqqqq/qqqqqqqq/8/8/8/8/4K3 w - - 0 1"), [PositionProblem::TooManyPieces(Colours::BLACK)]);
+    }
+
+    #[test]
+    fn validation_rejects_pawns_on_the_back_rank() {
+        assert_eq!(get_fen_problems("4k2p/8/8/8/8/8/8/4K3 b - - 0 1"), [PositionProblem::PawnOnBackRank(make_square(FILE_H, RANK_8))]);
+    }
+
+    #[test]
+    fn validation_rejects_the_side_not_to_move_in_check() {
+        assert_eq!(get_fen_problems("4k3/8/8/8/8/8/8/R3K3 w - - 0 1"), []);
+        assert_eq!(get_fen_problems("R3k3/8/8/8/8/8/8/4K3 w - - 0 1"), [PositionProblem::OpponentInCheck]);
+    }
+
+    #[test]
+    fn validation_checks_the_en_passant_square() {
+        assert_eq!(get_fen_problems("4k3/8/8/8/4P3/8/8/4K3 b - e3 0 1"), []);
+        assert_eq!(get_fen_problems("4k3/8/8/8/8/8/4P3/4K3 b - e3 0 1"), [PositionProblem::InvalidEnPassant]);
+    }
+
+    fn get_generated_moves(game_position: &mut Position, generate: fn(&mut Position, &mut MoveList)) -> Vec<Move> {
+        let mut move_list = new_move_list();
+        generate(game_position, &mut move_list);
+        return get_moves(&move_list).iter().map(|cm| unpack_move(game_position, *cm)).collect();
+    }
+
+    fn gives_check(game_position: &mut Position, m: Move) -> bool {
+        play_move(game_position, m);
+        let check = is_in_check(game_position);
+        undo_move(game_position);
+        return check;
+    }
+
+    fn same_moves(a: &[Move], b: &[Move]) -> bool {
+        return a.len() == b.len() && a.iter().all(|m| b.contains(m));
+    }
+
+    // the captures, quiet checks and evasions generators must each give only what they say, and
+    // together with the quiet moves that don't give check, every legal move exactly once
+    fn assert_generators_agree(game_position: &mut Position, legal_moves: &[Move], game: &str) {
+        let fen = format!("{} ({})", position_to_fen(game_position), game);
+        let captures = get_generated_moves(game_position, generate_captures);
+        let quiet_checks = get_generated_moves(game_position, generate_quiet_checks);
+        let evasions = get_generated_moves(game_position, generate_evasions);
+        for m in &captures {
+            assert!(is_capture_move(*m) || m.promotion != BLANK_PIECE, "{} from {} is not a capture", move_to_uci(game_position, *m), fen);
+        }
+        for m in &quiet_checks {
+            assert!(!is_capture_move(*m) && m.promotion == BLANK_PIECE && gives_check(game_position, *m),
+                "{} from {} is not a quiet check", move_to_uci(game_position, *m), fen);
+        }
+        if is_in_check(game_position) {
+            assert!(same_moves(&evasions, legal_moves), "evasions from {} are not the legal moves", fen);
+        } else {
+            assert!(evasions.is_empty(), "evasions from {} when not in check", fen);
+        }
+
+        let mut union = captures;
+        union.extend(quiet_checks);
+        for m in legal_moves {
+            if union.contains(m) {
+                continue;
+            }
+            assert!(!is_capture_move(*m) && m.promotion == BLANK_PIECE && !gives_check(game_position, *m),
+                "{} from {} is missing from the generators", move_to_uci(game_position, *m), fen);
+            union.push(*m);
+        }
+        assert!(same_moves(&union, legal_moves), "the generators give moves from {} that aren't legal", fen);
+    }
+
+    // the attack map, checkers and pins against working them out square by square: a piece is
+    // pinned if taking it off the board lets a new piece attack its king
+    fn assert_attack_queries_agree(game_position: &Position, game: &str) {
+        let fen = format!("{} ({})", position_to_fen(game_position), game);
+        let colour = get_turn_colour(game_position);
+        for by in [Colours::WHITE, Colours::BLACK] {
+            let attack_map = get_attack_map(game_position, by);
+            for sq in all_squares() {
+                assert_eq!(attack_map & square_bit(sq) != 0, is_square_attacked(game_position, sq, by), "attack map from {} on {}", fen, sq);
+            }
+        }
+
+        let king_sq = get_king_square(game_position, colour).unwrap();
+        let enemy = get_opposite_colour(colour);
+        let attackers = get_square_attackers(game_position, king_sq, enemy);
+        assert_eq!(get_checkers(game_position), attackers, "checkers from {}", fen);
+        let pins = get_pins(game_position, colour);
+        let mut pinned = 0;
+        for sq in all_squares() {
+            if get_piece_on(game_position, sq).colour != colour || sq == king_sq {
+                continue;
+            }
+            let mut without_piece = game_position.clone();
+            set_piece(&mut without_piece, sq, BLANK_PIECE);
+            let pinners = get_square_attackers(&without_piece, king_sq, enemy) & !attackers;
+            let pin = pins.iter().find(|pin| pin.sq == sq);
+            if pinners == 0 {
+                assert!(pin.is_none(), "{} from {} is not pinned", sq, fen);
+                continue;
+            }
+            assert_eq!(pinners.count_ones(), 1, "{} from {} is pinned twice", sq, fen);
+            let pinner = get_lsb(pinners);
+            let pin = pin.unwrap_or_else(|| panic!("{} from {} is pinned", sq, fen));
+            assert_eq!(pin.ray, get_squares_between(king_sq, pinner) | square_bit(pinner), "pin ray from {} for {}", fen, sq);
+            pinned |= square_bit(sq);
+        }
+        assert_eq!(pins.len(), pinned.count_ones() as usize, "pins from {}", fen);
+        assert_eq!(get_pinned_pieces(game_position, colour), pinned, "pinned pieces from {}", fen);
+    }
+
+    // checks every legal move as it goes, then undoes the whole game one move at a time. the
+    // seed and start position are in every failure message, which is all it takes to replay it
+    fn play_random_game(start_fen: &str, seed: u64, max_plies: usize) {
+        let game = format!("seed {} from {}", seed, start_fen);
+        let mut rng = StdRng::seed_from_u64(seed);
+        let mut game_position = position_from_fen(start_fen).unwrap();
+        let mut snapshots = vec![];
+        for _ in 0..max_plies {
+            let legal_moves = get_all_legal_moves(&mut game_position);
+            if legal_moves.is_empty() {
+                break;
+            }
+            assert_generators_agree(&mut game_position, &legal_moves, &game);
+            assert_attack_queries_agree(&game_position, &game);
+            let fen = format!("{} ({})", position_to_fen(&game_position), game);
+            for m in &legal_moves {
+                let uci = move_to_uci(&game_position, *m);
+                assert!(unpack_move(&game_position, pack_move(*m)) == *m, "{} from {} doesn't survive packing", uci, fen);
+                assert!(move_from_uci(&mut game_position, &uci) == Ok(*m), "{} from {} doesn't read back", uci, fen);
+                let san = move_to_san(&mut game_position, *m);
+                assert!(move_from_san(&mut game_position, &san) == Ok(*m), "{} from {} doesn't read back from SAN {}", uci, fen, san);
+                let before = game_position.clone();
+                play_move(&mut game_position, *m);
+                undo_move(&mut game_position);
+                assert!(game_position == before, "undoing {} from {} changed the position", uci, fen);
+            }
+            snapshots.push(game_position.clone());
+            let m = legal_moves[rng.random_range(0..legal_moves.len())];
+            play_move(&mut game_position, m);
+            assert_eq!(game_position.zobrist_key, compute_zobrist_key(&game_position), "incremental key for {} ({})", position_to_fen(&game_position), game);
+        }
+        while let Some(snapshot) = snapshots.pop() {
+            undo_move(&mut game_position);
+            assert!(game_position == snapshot, "expected {} after undo, got {} ({})", position_to_fen(&snapshot), position_to_fen(&game_position), game);
+        }
+    }
+
+    // start positions for the random games
+    const RANDOM_GAME_FENS: [&str; 5] = [
+        STARTING_FEN,
+        "r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1",
+        "8/8/1k6/2b5/2pP4/8/5K2/8 b - d3 0 1",
+        "r3k2r/Pppp1ppp/1b3nbN/nP6/BBP1P3/q4N2/Pp1P2PP/R2Q1RK1 w kq - 0 1",
+        // en passant takes the checking pawn
+        "8/8/8/2k5/3Pp3/8/8/4K3 b - d3 0 1",
+    ];
+
+    // one game from each start position per seed
+    #[test]
+    fn random_games_play_and_undo() {
+        for seed in 0..5 {
+            for fen in RANDOM_GAME_FENS {
+                play_random_game(fen, seed, 200);
+            }
+        }
+    }
+}
@@ -0,0 +1,217 @@
+use serde::{Deserialize, Serialize};
+use crate::board::*;
+use crate::rules::*;
+use crate::notation::*;
+use crate::record::*;
+use crate::square::*;
+
+// the forms pieces, moves and positions take when serialized, the field names are part of the
+// format so shouldn't be changed
+
+impl From<Piece> for String {
+    fn from(piece: Piece) -> String {
+        return piece_to_fen_char(piece).to_string();
+    }
+}
+
+impl TryFrom<String> for Piece {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Piece, String> {
+        if s == "." {
+            return Ok(BLANK_PIECE);
+        }
+        let mut chars = s.chars();
+        return match (chars.next().and_then(piece_from_fen_char), chars.next()) {
+            (Some(piece), None) => Ok(piece),
+            _ => Err(format!("invalid piece '{}'", s)),
+        };
+    }
+}
+
+impl From<Square> for String {
+    fn from(sq: Square) -> String {
+        return get_square_name(sq);
+    }
+}
+
+impl TryFrom<String> for Square {
+    type Error = String;
+
+    fn try_from(name: String) -> Result<Square, String> {
+        return square_from_name(&name).ok_or(format!("invalid square '{}'", name));
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SerializedMove {
+    from: Square,
+    to: Square,
+    piece: Piece,
+    captured: Option<Piece>, // for castling this is the king's own rook
+    promotion: Option<Piece>,
+}
+
+fn none_if_blank(piece: Piece) -> Option<Piece> {
+    if piece.piece_type == PieceTypes::BLANK {
+        return None;
+    }
+    return Some(piece);
+}
+
+impl From<Move> for SerializedMove {
+    fn from(m: Move) -> SerializedMove {
+        return SerializedMove {
+            from: m.start,
+            to: m.end,
+            piece: m.start_piece,
+            captured: none_if_blank(m.end_piece),
+            promotion: none_if_blank(m.promotion),
+        };
+    }
+}
+
+impl TryFrom<SerializedMove> for Move {
+    type Error = String;
+
+    fn try_from(fields: SerializedMove) -> Result<Move, String> {
+        if fields.piece.piece_type == PieceTypes::BLANK {
+            return Err("a move needs a piece to move".to_string());
+        }
+        return Ok(Move {
+            start: fields.from,
+            end: fields.to,
+            start_piece: fields.piece,
+            end_piece: fields.captured.unwrap_or(BLANK_PIECE),
+            promotion: fields.promotion.unwrap_or(BLANK_PIECE),
+        });
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub(crate) struct SerializedPosition {
+    fen: String,
+    start_fen: String,
+    chess960: bool,
+    moves: Vec<Move>,
+}
+
+impl From<Position> for SerializedPosition {
+    fn from(game_position: Position) -> SerializedPosition {
+        let record = get_game_record(&game_position, GameOutcome::Ongoing);
+        return SerializedPosition {
+            fen: position_to_fen(&game_position),
+            start_fen: record.start_fen,
+            chess960: record.chess960,
+            moves: record.moves,
+        };
+    }
+}
+
+impl TryFrom<SerializedPosition> for Position {
+    type Error = String;
+
+    fn try_from(fields: SerializedPosition) -> Result<Position, String> {
+        let record = GameRecord {
+            start_fen: fields.start_fen,
+            chess960: fields.chess960,
+            moves: fields.moves,
+            outcome: GameOutcome::Ongoing,
+        };
+        let game_position = position_from_game_record(&record).map_err(|error| error.to_string())?;
+        // the fen is stored as well so it can be read without replaying, so they have to agree
+        if position_to_fen(&game_position) != fields.fen {
+            return Err(format!("the moves lead to '{}', not '{}'", position_to_fen(&game_position), fields.fen));
+        }
+        return Ok(game_position);
+    }
+}
+
+#[cfg(all(test, feature = "serde"))]
+mod tests {
+    use super::*;
+    use serde::de::DeserializeOwned;
+
+    fn json_round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        return serde_json::from_str(&serde_json::to_string(value).unwrap()).unwrap();
+    }
+
+    fn bincode_round_trip<T: Serialize + DeserializeOwned>(value: &T) -> T {
+        return bincode::deserialize(&bincode::serialize(value).unwrap()).unwrap();
+    }
+
+    fn play_uci(game_position: &mut Position, moves: &[&str]) {
+        for uci in moves {
+            let m = move_from_uci(game_position, uci).unwrap();
+            play_move(game_position, m);
+        }
+    }
+
+    #[test]
+    fn pieces() {
+        for c in "KQRBNPkqrbnp".chars() {
+            let piece = piece_from_fen_char(c).unwrap();
+            assert_eq!(serde_json::to_string(&piece).unwrap(), format!("\"{}\"", c));
+            assert!(json_round_trip(&piece) == piece);
+            assert!(bincode_round_trip(&piece) == piece);
+        }
+        assert_eq!(serde_json::to_string(&BLANK_PIECE).unwrap(), "\".\"");
+        assert!(json_round_trip(&BLANK_PIECE) == BLANK_PIECE);
+        assert!(serde_json::from_str::<Piece>("\"Kq\"").is_err());
+        assert!(serde_json::from_str::<Piece>("\"x\"").is_err());
+    }
+
+    #[test]
+    fn moves() {
+        let mut game_position = position_from_fen("r3k3/1P6/8/8/8/8/8/R3K2R w KQq - 0 1").unwrap();
+        let moves = get_all_legal_moves(&mut game_position);
+        assert!(moves.iter().any(|m| is_castling_move(*m)));
+        for m in &moves {
+            assert!(json_round_trip(m) == *m, "{}", move_to_uci(&game_position, *m));
+            assert!(bincode_round_trip(m) == *m, "{}", move_to_uci(&game_position, *m));
+        }
+
+        let m = move_from_uci(&mut game_position, "b7a8q").unwrap();
+        assert_eq!(serde_json::to_string(&m).unwrap(), r#"{"from":"b7","to":"a8","piece":"P","captured":"r","promotion":"Q"}"#);
+        assert!(serde_json::from_str::<Move>(r#"{"from":"z2","to":"e4","piece":"P","captured":null,"promotion":null}"#).is_err());
+        assert!(serde_json::from_str::<Move>(r#"{"from":"e2","to":"e4","piece":".","captured":null,"promotion":null}"#).is_err());
+    }
+
+    // the undo history comes back too, so the moves can still be taken back
+    #[test]
+    fn position_with_undo_history() {
+        let mut game_position = position_from_fen(STARTING_FEN).unwrap();
+        play_uci(&mut game_position, &["e2e4", "d7d5", "e4d5", "g8f6"]);
+        for back in [json_round_trip(&game_position), bincode_round_trip(&game_position)] {
+            assert!(back == game_position);
+            assert_eq!(back.undo_history.len(), 4);
+        }
+    }
+
+    #[test]
+    fn position_from_fen_without_moves() {
+        let game_position = position_from_fen("r3k2r/p1ppqpb1/bn2pnp1/3PN3/1p2P3/2N2Q1p/PPPBBPPP/R3K2R w KQkq - 0 1").unwrap();
+        assert!(json_round_trip(&game_position) == game_position);
+        assert!(bincode_round_trip(&game_position) == game_position);
+    }
+
+    #[test]
+    fn chess960_position() {
+        let mut game_position = position_from_fen("1r4kr/8/8/8/8/8/8/1R4KR w HBhb - 0 1").unwrap();
+        // castling where the king is already on g1, which reads differently in Chess960
+        play_uci(&mut game_position, &["g1h1", "b8b2"]);
+        assert!(is_castling_move(game_position.undo_history[0].m));
+        for back in [json_round_trip(&game_position), bincode_round_trip(&game_position)] {
+            assert!(back == game_position);
+            assert!(back.chess960);
+        }
+    }
+
+    #[test]
+    fn position_must_match_its_fen() {
+        let json = r#"{"fen":"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1","start_fen":"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1","chess960":false,"moves":[{"from":"e2","to":"e4","piece":"P","captured":null,"promotion":null}]}"#;
+        assert!(serde_json::from_str::<Position>(json).is_err());
+        let json = r#"{"fen":"x","start_fen":"rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w KQkq - 0 1","chess960":false,"moves":[{"from":"e2","to":"e5","piece":"P","captured":null,"promotion":null}]}"#;
+        assert!(serde_json::from_str::<Position>(json).is_err());
+    }
+}
@@ -0,0 +1,226 @@
+use std::fmt;
+
+// a to h, stored as 0 to 7
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct File(u8);
+
+// 1 to 8, stored as 0 to 7
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+pub struct Rank(u8);
+
+// numbered the same way as bitboard bits, a1 = 0, h1 = 7, h8 = 63. the only ways to make one
+// are from a file and rank or through functions that check it is on the board
+#[derive(Debug, Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize), serde(into = "String", try_from = "String"))]
+pub struct Square(u8);
+
+pub const FILE_A: File = File(0);
+pub const FILE_B: File = File(1);
+pub const FILE_C: File = File(2);
+pub const FILE_D: File = File(3);
+pub const FILE_E: File = File(4);
+pub const FILE_F: File = File(5);
+pub const FILE_G: File = File(6);
+pub const FILE_H: File = File(7);
+
+pub const RANK_1: Rank = Rank(0);
+pub const RANK_2: Rank = Rank(1);
+pub const RANK_3: Rank = Rank(2);
+pub const RANK_4: Rank = Rank(3);
+pub const RANK_5: Rank = Rank(4);
+pub const RANK_6: Rank = Rank(5);
+pub const RANK_7: Rank = Rank(6);
+pub const RANK_8: Rank = Rank(7);
+
+pub fn file_from_index(x: usize) -> Option<File> {
+    if x > 7 {
+        return None;
+    }
+    return Some(File(x as u8));
+}
+
+pub fn rank_from_index(y: usize) -> Option<Rank> {
+    if y > 7 {
+        return None;
+    }
+    return Some(Rank(y as u8));
+}
+
+pub fn square_from_index(index: usize) -> Option<Square> {
+    if index > 63 {
+        return None;
+    }
+    return Some(Square(index as u8));
+}
+
+// for indices already known to be on the board, e.g. a set bit of a bitboard
+pub(crate) const fn square_from_index_unchecked(index: usize) -> Square {
+    debug_assert!(index < 64);
+    return Square(index as u8);
+}
+
+// (0, 0) is a1, anything off the board gives None
+pub fn square_from_coordinates(x: i32, y: i32) -> Option<Square> {
+    if !(0..8).contains(&x) || !(0..8).contains(&y) {
+        return None;
+    }
+    return Some(Square((y * 8 + x) as u8));
+}
+
+pub const fn get_file_index(file: File) -> usize {
+    return file.0 as usize;
+}
+
+pub const fn get_rank_index(rank: Rank) -> usize {
+    return rank.0 as usize;
+}
+
+pub const fn get_square_index(sq: Square) -> usize {
+    return sq.0 as usize;
+}
+
+pub const fn make_square(file: File, rank: Rank) -> Square {
+    return Square(rank.0 * 8 + file.0);
+}
+
+pub const fn get_square_file(sq: Square) -> File {
+    return File(sq.0 % 8);
+}
+
+pub const fn get_square_rank(sq: Square) -> Rank {
+    return Rank(sq.0 / 8);
+}
+
+// the file and rank indices, e.g. for indexing the board or drawing
+pub const fn get_square_coordinates(sq: Square) -> (usize, usize) {
+    return ((sq.0 % 8) as usize, (sq.0 / 8) as usize);
+}
+
+/////////////////////////////////////////////////////////////////
+// offsets, which give None when they would leave the board
+
+pub fn offset_file(file: File, dx: i32) -> Option<File> {
+    let x = file.0 as i32 + dx;
+    if !(0..8).contains(&x) {
+        return None;
+    }
+    return Some(File(x as u8));
+}
+
+pub fn offset_rank(rank: Rank, dy: i32) -> Option<Rank> {
+    let y = rank.0 as i32 + dy;
+    if !(0..8).contains(&y) {
+        return None;
+    }
+    return Some(Rank(y as u8));
+}
+
+pub fn offset_square(sq: Square, dx: i32, dy: i32) -> Option<Square> {
+    let (x, y) = get_square_coordinates(sq);
+    return square_from_coordinates(x as i32 + dx, y as i32 + dy);
+}
+
+/////////////////////////////////////////////////////////////////
+// iteration, in index order
+
+pub fn all_files() -> impl DoubleEndedIterator<Item = File> {
+    return (0..8).map(File);
+}
+
+pub fn all_ranks() -> impl DoubleEndedIterator<Item = Rank> {
+    return (0..8).map(Rank);
+}
+
+pub fn all_squares() -> impl DoubleEndedIterator<Item = Square> {
+    return (0..64).map(Square);
+}
+
+/////////////////////////////////////////////////////////////////
+// algebraic names
+
+pub fn file_from_char(c: char) -> Option<File> {
+    if !('a'..='h').contains(&c) {
+        return None;
+    }
+    return Some(File(c as u8 - b'a'));
+}
+
+pub fn rank_from_char(c: char) -> Option<Rank> {
+    if !('1'..='8').contains(&c) {
+        return None;
+    }
+    return Some(Rank(c as u8 - b'1'));
+}
+
+pub fn get_file_char(file: File) -> char {
+    return (b'a' + file.0) as char;
+}
+
+pub fn get_rank_char(rank: Rank) -> char {
+    return (b'1' + rank.0) as char;
+}
+
+// e.g. "e2" -> e2, anything but a file letter followed by a rank digit gives None
+pub fn square_from_name(name: &str) -> Option<Square> {
+    let mut chars = name.chars();
+    return match (chars.next(), chars.next(), chars.next()) {
+        (Some(file), Some(rank), None) => Some(make_square(file_from_char(file)?, rank_from_char(rank)?)),
+        _ => None,
+    };
+}
+
+pub fn get_square_name(sq: Square) -> String {
+    return format!("{}{}", get_file_char(get_square_file(sq)), get_rank_char(get_square_rank(sq)));
+}
+
+impl fmt::Display for File {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", get_file_char(*self))
+    }
+}
+
+impl fmt::Display for Rank {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", get_rank_char(*self))
+    }
+}
+
+impl fmt::Display for Square {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}", get_square_name(*self))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn squares_read_back() {
+        for sq in all_squares() {
+            let (x, y) = get_square_coordinates(sq);
+            assert_eq!(square_from_name(&get_square_name(sq)), Some(sq));
+            assert_eq!(square_from_coordinates(x as i32, y as i32), Some(sq));
+            assert_eq!(square_from_index(get_square_index(sq)), Some(sq));
+            assert_eq!(make_square(get_square_file(sq), get_square_rank(sq)), sq);
+        }
+    }
+
+    #[test]
+    fn offsets_stop_at_the_edge_of_the_board() {
+        for sq in all_squares() {
+            let (x, y) = get_square_coordinates(sq);
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1), (2, 1), (-7, -7)] {
+                let on_board = (0..8).contains(&(x as i32 + dx)) && (0..8).contains(&(y as i32 + dy));
+                assert_eq!(offset_square(sq, dx, dy).is_some(), on_board, "({}, {}) from {}", dx, dy, sq);
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_square_names() {
+        for name in ["", "e", "i1", "a0", "a9", "e2e4", "E2"] {
+            assert_eq!(square_from_name(name), None, "{}", name);
+        }
+    }
+}
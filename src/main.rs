@@ -1,144 +1,300 @@
 mod draw;
-mod engine;
-mod rules;
 
+use chess_core::board::*;
+use chess_core::rules::*;
+use chess_core::engine::*;
+use chess_core::notation::*;
+use chess_core::perft::*;
+use chess_core::square::*;
 use draw::*;
-use rules::*;
-use engine::*;
 
 use raylib::prelude::*;
 use raylib::consts::KeyboardKey::*;
 use std::collections::HashMap;
 
-fn get_player_move(rl: &mut RaylibHandle, game_position: &mut Position, thread: &RaylibThread, piece_images_map: &HashMap<PieceNames, Texture2D>) -> Move {
-    let mut previous_mouse_x = 99;
-    let mut previous_mouse_y = 99;
+enum PlayerAction {
+    Play(Move),
+    Resign,
+    ClaimDraw,
+    NewChess960Game,
+    Quit,
+}
+
+fn get_player_move(rl: &mut RaylibHandle, game_position: &mut Position, board_view: &mut BoardView, thread: &RaylibThread, piece_images_map: &HashMap<PieceNames, Texture2D>) -> PlayerAction {
+    board_view.selected_square = None;
     loop {
-        let mouse_x = (rl.get_mouse_x() / 60) as usize;
-        let mouse_y = (rl.get_mouse_y() / 60) as usize;
+        // None when the mouse is off the board
+        let mouse_square = square_from_coordinates(rl.get_mouse_x() / 60, 7 - rl.get_mouse_y() / 60);
 
-        draw_board(rl, &thread, game_position, &piece_images_map, false);
+        draw_board(rl, &thread, game_position, board_view, &piece_images_map, GameOutcome::Ongoing, None);
 
         if rl.is_key_down(KEY_Q) {
-            return NULL_MOVE;
+            return PlayerAction::Quit;
+        }
+        if rl.is_key_pressed(KEY_R) {
+            return PlayerAction::Resign;
+        }
+        // the fifty-move draw only ends the game once it is claimed
+        if rl.is_key_pressed(KEY_D) && can_claim_fifty_move_draw(game_position) {
+            return PlayerAction::ClaimDraw;
+        }
+        if rl.is_key_pressed(KEY_N) {
+            return PlayerAction::NewChess960Game;
         }
         if rl.is_key_pressed(KEY_U) {
             undo_move(game_position);
+            remove_legal_highlights(board_view);
+            update_hanging_pieces(board_view, game_position);
         }
 
         if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
-            if mouse_x > 7 || mouse_y > 7{
+            let sq = match mouse_square {
+                Some(sq) => sq,
+                None => continue,
+            };
+            if game_position.turn != (get_piece_on(game_position, sq).colour as i8) {
                 continue;
             }
-            if game_position.turn != (game_position.board[mouse_x][7 - mouse_y].piece.colour as i8) {
-                continue;
-            }
-            remove_legal_highlights(game_position);
-            highlight_piece_legal_moves(game_position, mouse_x as i32, (7-mouse_y) as i32);
-            game_position.dragged_piece = game_position.board[mouse_x][7 - mouse_y].piece.piece_name;
-            previous_mouse_x = mouse_x;
-            previous_mouse_y = mouse_y;
+            remove_legal_highlights(board_view);
+            highlight_piece_legal_moves(board_view, game_position, sq);
+            board_view.dragged_piece = get_piece_on(game_position, sq).piece_name;
+            board_view.selected_square = Some(sq);
         }
 
 
         if rl.is_mouse_button_released(MouseButton::MOUSE_BUTTON_LEFT) {
-            game_position.dragged_piece = PieceNames::Blank;
-            if mouse_x > 7 || mouse_y > 7{
-                continue;
-            }
-            if previous_mouse_x > 7 || previous_mouse_y > 7{
+            board_view.dragged_piece = PieceNames::Blank;
+            let (start, end) = match (board_view.selected_square, mouse_square) {
+                (Some(start), Some(end)) => (start, end),
+                _ => continue,
+            };
+            if start == end {
                 continue;
             }
-            if previous_mouse_x == mouse_x && previous_mouse_y == mouse_y {
-                continue;
-            }
-            remove_legal_highlights(game_position);
-            if game_position.turn != (game_position.board[previous_mouse_x][7 - previous_mouse_y].piece.colour as i8) {
+            remove_legal_highlights(board_view);
+            if game_position.turn != (get_piece_on(game_position, start).colour as i8) {
                 continue; // is our turn
             }
-            if game_position.turn == (game_position.board[mouse_x][7 - mouse_y].piece.colour as i8) {
-                continue; // can not capture own piece
-            }
 
             // is legal
-            let m = create_move(game_position, previous_mouse_x, 7-previous_mouse_y, mouse_x, 7-mouse_y);
             let all_legal_moves = get_all_legal_moves(game_position);
-            if !all_legal_moves.contains(&m) {
-                continue;
+            let mut m = match get_dropped_move(&all_legal_moves, start, end) {
+                Some(m) => m,
+                None => continue,
+            };
+            if is_promotion_move(m) {
+                m.promotion = get_promotion_choice(rl, game_position, board_view, thread, piece_images_map, m);
+                if m.promotion == BLANK_PIECE {
+                    board_view.selected_square = None;
+                    continue;
+                }
             }
 
-            return m;
+            return PlayerAction::Play(m);
+        }
+    }
+}
+
+// the legal move made by dragging a piece from start to end. castling can be played by dropping
+// the king on its destination or, as is needed in some Chess960 positions, on its own rook
+fn get_dropped_move(legal_moves: &[Move], start: Square, end: Square) -> Option<Move> {
+    let from_start = legal_moves.iter().filter(|m| m.start == start);
+    let mut castling_move = None;
+    for m in from_start {
+        if m.end == end {
+            return Some(*m);
+        }
+        if is_castling_move(*m) && make_square(get_castling_destination_files(*m).0, get_square_rank(m.end)) == end {
+            castling_move = Some(*m);
         }
     }
+    return castling_move;
 }
 
-fn check_game_over(rl: &mut RaylibHandle, thread: &RaylibThread, game_position: &mut Position, piece_images_map: &HashMap<PieceNames, Texture2D>)  -> bool {
-    let game_over = get_all_legal_moves(game_position).len() == 0;
-    if game_over {
-        loop {
-            draw_board(rl, &thread, game_position, &piece_images_map, true);
-            if rl.is_key_down(KEY_Q) {
-                break;
+// returns BLANK_PIECE if the player clicks away from the menu
+fn get_promotion_choice(rl: &mut RaylibHandle, game_position: &Position, board_view: &BoardView, thread: &RaylibThread, piece_images_map: &HashMap<PieceNames, Texture2D>, m: Move) -> Piece {
+    loop {
+        draw_board(rl, &thread, game_position, board_view, &piece_images_map, GameOutcome::Ongoing, Some(m));
+
+        if rl.is_key_down(KEY_Q) {
+            return BLANK_PIECE;
+        }
+
+        if rl.is_mouse_button_pressed(MouseButton::MOUSE_BUTTON_LEFT) {
+            let mouse_x = (rl.get_mouse_x() / 60) as usize;
+            let mouse_y = (rl.get_mouse_y() / 60) as usize;
+            if mouse_x != get_file_index(get_square_file(m.end)) || mouse_y > 7 {
+                return BLANK_PIECE;
+            }
+            for (i, piece) in get_promotion_pieces(m.start_piece.colour).iter().enumerate() {
+                if get_promotion_menu_y(m, i) == 7 - mouse_y {
+                    return *piece;
+                }
             }
+            return BLANK_PIECE;
+        }
+    }
+}
+
+fn show_game_over(rl: &mut RaylibHandle, thread: &RaylibThread, game_position: &Position, board_view: &BoardView, piece_images_map: &HashMap<PieceNames, Texture2D>, outcome: GameOutcome) {
+    loop {
+        draw_board(rl, &thread, game_position, board_view, &piece_images_map, outcome, None);
+        if rl.is_key_down(KEY_Q) || rl.window_should_close() {
+            break;
         }
+    }
+}
+
+fn check_game_over(rl: &mut RaylibHandle, thread: &RaylibThread, game_position: &mut Position, board_view: &BoardView, piece_images_map: &HashMap<PieceNames, Texture2D>)  -> bool {
+    let outcome = get_game_outcome(game_position);
+    if outcome != GameOutcome::Ongoing {
+        show_game_over(rl, thread, game_position, board_view, piece_images_map, outcome);
         return true;
     } 
-    draw_board(rl, &thread, game_position, &piece_images_map, game_over);
+    draw_board(rl, &thread, game_position, board_view, &piece_images_map, outcome, None);
     return false;
 }
 
-fn main() {
-    let (mut rl, thread) = raylib::init()
-        .size(480, 480)
-        .title("Chess")
-        .build();
-
-    let piece_images_map: HashMap<PieceNames, Texture2D> = initialise_images(&mut rl, &thread);
-    let game_board = [[Square {piece: BLANK_PIECE, highlight: Highlights::NORMAL}; 8]; 8];
-    let mut game_position = Position { 
-        board: game_board, 
-        turn: 1, 
-        dragged_piece: PieceNames::Blank, 
-        en_passant: -1,
-        castling_rights_history: vec![], // W o-o, B o-o, W o-o-o, B o-o-o
-        move_history: vec![],
+// reads the FEN given on the command line, or says what is wrong with it and exits
+fn read_fen_argument(fen: &str) -> Position {
+    return match position_from_fen(fen) {
+        Ok(game_position) => game_position,
+        Err(e) => {
+            eprintln!("bad FEN '{}': {}", fen, e);
+            std::process::exit(1);
+        }
     };
-    initialise_board(&mut game_position);
+}
 
-    while !rl.window_should_close() {
+// headless commands:
+//   perft <depth> [fen]
+//   divide <depth> [fen]
+// returns false if the arguments are not a command, so the GUI should start
+fn run_command(args: &[String]) -> bool {
+    if args.len() < 2 {
+        return false;
+    }
+    match args[1].as_str() {
+        "perft" | "divide" => {
+            let depth: usize = match args.get(2).and_then(|d| d.parse().ok()) {
+                Some(depth) => depth,
+                None => {
+                    eprintln!("usage: {} <depth> [fen]", args[1]);
+                    std::process::exit(1);
+                }
+            };
+            let fen = args.get(3).map(|f| f.as_str()).unwrap_or(STARTING_FEN);
+            let mut game_position = read_fen_argument(fen);
+            if args[1] == "perft" {
+                println!("{}", perft(&mut game_position, depth));
+            } else {
+                let counts = divide(&mut game_position, depth);
+                for (m, nodes) in &counts {
+                    println!("{}: {}", move_to_uci(&game_position, *m), nodes);
+                }
+                println!();
+                println!("nodes: {}", counts.iter().map(|(_, nodes)| nodes).sum::<u64>());
+            }
+        }
+        _ => {
+            return false;
+        }
+    }
+    return true;
+}
 
+fn new_chess960_game(index: usize) -> Position {
+    println!("Chess960 start position {}", index);
+    let mut game_position = empty_position();
+    initialise_chess960_board(&mut game_position, index);
+    return game_position;
+}
 
+fn main() {
+    let args: Vec<String> = std::env::args().collect();
+    if run_command(&args) {
+        return;
+    }
 
-        // white's turn
-        let white_move: Move = get_player_move(&mut rl, &mut game_position, &thread, &piece_images_map); 
-        if white_move == NULL_MOVE {
-            break;
+    // optionally start from a FEN, or a Chess960 start position, given on the command line
+    let mut game_position: Position;
+    match args.get(1) {
+        Some(arg) if arg == "chess960" => {
+            let index = args.get(2).and_then(|i| i.parse().ok()).unwrap_or_else(get_random_chess960_index);
+            game_position = new_chess960_game(index);
         }
-        /*
-        let white_move = get_engine_move(&mut game_position);
-        */
-        play_move(&mut game_position, white_move);
-        if check_game_over(&mut rl, &thread, &mut game_position, &piece_images_map) {
-            break;
+        Some(fen) => {
+            game_position = read_fen_argument(fen);
         }
+        None => {
+            game_position = empty_position();
+            initialise_board(&mut game_position);
+        }
+    }
 
+    let (mut rl, thread) = raylib::init()
+        .size(480, 480)
+        .title("Chess")
+        .build();
 
+    let piece_images_map: HashMap<PieceNames, Texture2D> = initialise_images(&mut rl, &thread);
+    let mut board_view = new_board_view();
 
-
-        // black's turn
-        /*
-        let black_move: Move = get_player_move(&mut rl, &mut game_position, &thread, &piece_images_map); 
-        if black_move == NULL_MOVE {
-            break;
-        }
-        */
-        let black_move = get_engine_move(&mut game_position);
-        play_move(&mut game_position, black_move);
-        if check_game_over(&mut rl, &thread, &mut game_position, &piece_images_map) {
+    while !rl.window_should_close() {
+        // a move has been played or a new game started since the last time round
+        update_hanging_pieces(&mut board_view, &game_position);
+        if check_game_over(&mut rl, &thread, &mut game_position, &board_view, &piece_images_map) {
             break;
         }
 
-
-
+        let m: Move;
+        if game_position.turn == 1 {
+            // white's turn
+            match get_player_move(&mut rl, &mut game_position, &mut board_view, &thread, &piece_images_map) {
+                PlayerAction::Play(player_move) => m = player_move,
+                PlayerAction::Resign => {
+                    let outcome = GameOutcome::Resignation { winner: Colours::BLACK };
+                    show_game_over(&mut rl, &thread, &game_position, &board_view, &piece_images_map, outcome);
+                    break;
+                }
+                PlayerAction::ClaimDraw => {
+                    show_game_over(&mut rl, &thread, &game_position, &board_view, &piece_images_map, GameOutcome::FiftyMoveRule);
+                    break;
+                }
+                PlayerAction::NewChess960Game => {
+                    game_position = new_chess960_game(get_random_chess960_index());
+                    board_view = new_board_view();
+                    continue;
+                }
+                PlayerAction::Quit => break,
+            }
+            /*
+            m = get_engine_move(&mut game_position);
+            */
+        } else {
+            // black's turn
+            /*
+            match get_player_move(&mut rl, &mut game_position, &mut board_view, &thread, &piece_images_map) {
+                PlayerAction::Play(player_move) => m = player_move,
+                PlayerAction::Resign => {
+                    let outcome = GameOutcome::Resignation { winner: Colours::WHITE };
+                    show_game_over(&mut rl, &thread, &game_position, &board_view, &piece_images_map, outcome);
+                    break;
+                }
+                PlayerAction::ClaimDraw => {
+                    show_game_over(&mut rl, &thread, &game_position, &board_view, &piece_images_map, GameOutcome::FiftyMoveRule);
+                    break;
+                }
+                PlayerAction::NewChess960Game => {
+                    game_position = new_chess960_game(get_random_chess960_index());
+                    board_view = new_board_view();
+                    continue;
+                }
+                PlayerAction::Quit => break,
+            }
+            */
+            m = get_engine_move(&mut game_position);
+        }
+        play_move(&mut game_position, m);
     }
 }